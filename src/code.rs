@@ -0,0 +1,123 @@
+//! Element for Quill code blocks.
+
+use genpdf::{
+    error::Error,
+    render::Area,
+    style::{Color, Style},
+    Context, Element, Margins, Mm, Position, RenderResult, Size,
+};
+
+use crate::{
+    links::SharedLinks,
+    paragraph::{fill_rect, RichParagraph, Run},
+    theme::Spacing,
+};
+
+/// Spaces a tab is replaced with, fonts rarely have a glyph for tabs
+const TAB: &str = "    ";
+
+/// Consecutive code lines rendered in one box.
+/// Every line keeps its whitespace and is only broken if it is wider than the page.
+pub(crate) struct CodeBlock {
+    lines: Vec<RichParagraph>,
+    /// Style of the code on top of the document style
+    style: Style,
+    background: Option<Color>,
+    /// Space between the background box and the code
+    padding: Spacing,
+    /// Index of the next line to render as code blocks can span pages
+    next_line: usize,
+}
+
+impl CodeBlock {
+    pub(crate) fn new(
+        lines: Vec<Vec<Run>>,
+        style: Style,
+        background: Option<Color>,
+        padding: Spacing,
+    ) -> Self {
+        let lines = lines
+            .into_iter()
+            .map(|mut runs| {
+                for run in &mut runs {
+                    run.text = run.text.replace('\t', TAB);
+                }
+                RichParagraph::new(runs)
+            })
+            .collect();
+
+        Self {
+            lines,
+            style,
+            background,
+            padding,
+            next_line: 0,
+        }
+    }
+
+    /// Record the areas of links in `links`, the block is placed `left` of the page margin
+    pub(crate) fn with_links(mut self, links: Option<SharedLinks>, left: Mm) -> Self {
+        let left = left + self.padding.left;
+        self.lines = self
+            .lines
+            .into_iter()
+            .map(|line| line.with_links(links.clone(), left))
+            .collect();
+        self
+    }
+}
+
+impl Element for CodeBlock {
+    fn render(
+        &mut self,
+        context: &Context,
+        area: Area<'_>,
+        style: Style,
+    ) -> Result<RenderResult, Error> {
+        let style = style.and(self.style);
+        let padding = self.padding;
+        let width = area.size().width;
+        let code_width = width - padding.left - padding.right;
+        let max_height = area.size().height - padding.top - padding.bottom;
+
+        // The lines are measured first as the box has to be drawn before the code to stay behind it
+        let mut result = RenderResult::default();
+        let mut height = Mm::from(0);
+        for line in &mut self.lines[self.next_line..] {
+            let (line_height, has_more) =
+                line.measure(context, style, code_width, max_height - height);
+            height += line_height;
+            if has_more {
+                result.has_more = true;
+                break;
+            }
+        }
+
+        // Nothing fit on this page, the block starts on the next one
+        if height == Mm::from(0) && result.has_more {
+            return Ok(result);
+        }
+
+        result.size = Size::new(width, height + padding.top + padding.bottom);
+        if let Some(background) = self.background {
+            fill_rect(&area, Position::new(0, 0), result.size, background);
+        }
+
+        // The bottom padding is left out of the margins as the bottom of the area must not move
+        // for links to be placed, the measured height keeps the code above it instead
+        let mut code_area = area;
+        code_area.add_margins(Margins::trbl(padding.top, padding.right, 0, padding.left));
+        let mut rendered = Mm::from(0);
+        while let Some(line) = self.lines.get_mut(self.next_line) {
+            let line_result =
+                line.render_within(context, code_area.clone(), style, max_height - rendered)?;
+            code_area.add_offset(Position::new(0, line_result.size.height));
+            rendered += line_result.size.height;
+            if line_result.has_more {
+                break;
+            }
+            self.next_line += 1;
+        }
+        Ok(result)
+    }
+}
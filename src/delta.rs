@@ -1,76 +1,918 @@
+mod attributes;
+mod diff;
+mod iter;
+
+use std::fmt;
 use std::str::FromStr;
 
-use serde::Deserialize;
-use serde_with::{serde_as, EnumMap};
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize};
+use serde_with::serde_as;
 use url::Url;
 
-#[derive(Deserialize, Debug)]
+use attributes::AttributeMap;
+use diff::DiffType;
+use iter::{OpIterator, OpType};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Image {
     pub image: Url,
 }
 
-#[derive(Deserialize, Debug)]
+/// A video embedded by the URL of its player
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Video {
+    pub video: Url,
+}
+
+/// A formula in the TeX syntax of KaTeX
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Formula {
+    pub formula: String,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
 #[serde(untagged)]
 pub enum DeltaType {
     String(String),
     Image(Image),
+    Video(Video),
+    Formula(Formula),
+    /// An embed Quill does not define, like one added by a plugin
+    Other(serde_json::Map<String, serde_json::Value>),
+}
+
+/// Embeds that Quill defines must be valid, only other embeds are kept as they are
+impl<'de> Deserialize<'de> for DeltaType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match serde_json::Value::deserialize(deserializer)? {
+            serde_json::Value::String(text) => Ok(DeltaType::String(text)),
+            serde_json::Value::Object(embed) if embed.contains_key("image") => {
+                Image::deserialize(serde_json::Value::Object(embed))
+                    .map(DeltaType::Image)
+                    .map_err(D::Error::custom)
+            }
+            serde_json::Value::Object(embed) if embed.contains_key("video") => {
+                Video::deserialize(serde_json::Value::Object(embed))
+                    .map(DeltaType::Video)
+                    .map_err(D::Error::custom)
+            }
+            serde_json::Value::Object(embed) if embed.contains_key("formula") => {
+                Formula::deserialize(serde_json::Value::Object(embed))
+                    .map(DeltaType::Formula)
+                    .map_err(D::Error::custom)
+            }
+            serde_json::Value::Object(embed) => Ok(DeltaType::Other(embed)),
+            other => Err(D::Error::custom(format!(
+                "expected text or an embed, found {}",
+                other
+            ))),
+        }
+    }
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum ListType {
     Bullet,
     Ordered,
+    /// A completed item of a checklist
+    Checked,
+    /// An open item of a checklist
+    Unchecked,
 }
 
-#[derive(Deserialize, Debug)]
+impl From<String> for DeltaType {
+    fn from(text: String) -> Self {
+        DeltaType::String(text)
+    }
+}
+
+impl From<&str> for DeltaType {
+    fn from(text: &str) -> Self {
+        DeltaType::String(text.to_string())
+    }
+}
+
+impl From<Image> for DeltaType {
+    fn from(image: Image) -> Self {
+        DeltaType::Image(image)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SizeType {
+    Small,
+    Large,
+    Huge,
+    /// A CSS font size like `18px`
+    #[serde(untagged)]
+    Custom(String),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ScriptType {
+    Sub,
+    Super,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum CodeBlockType {
+    /// Quill 1 marks code blocks with `true`
+    Plain(bool),
+    /// Quill 2 stores the language of the code block, `plain` if there is none
+    Language(String),
+}
+
+impl CodeBlockType {
+    /// The language of the code block, if there is one
+    pub fn language(&self) -> Option<&str> {
+        match self {
+            CodeBlockType::Language(language) if language != "plain" => Some(language),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum AlignType {
+    Center,
+    Right,
+    Justify,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DirectionType {
+    Rtl,
+}
+
+/// The formats built into Quill.
+/// Inline formats apply to inserted text, block formats are set on the newline ending a line.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum Attribute {
+    // Inline formats
     Bold(bool),
     Italic(bool),
+    Underline(bool),
+    Strike(bool),
+    /// CSS colour of the text
+    Color(String),
+    /// CSS colour behind the text
+    Background(String),
+    /// Font family like `serif` or `monospace`
+    Font(String),
+    Size(SizeType),
+    Link(String),
+    Script(ScriptType),
+    Code(bool),
+
+    // Block formats
     Header(u8),
     List(ListType),
+    #[serde(rename = "code-block")]
+    CodeBlock(CodeBlockType),
+    Blockquote(bool),
+    Align(AlignType),
+    /// Indentation level between 1 and 8
+    Indent(u8),
+    Direction(DirectionType),
+
+    // Formats of image and video embeds
+    /// Width in pixels or any CSS length
+    Width(String),
+    /// Height in pixels or any CSS length
+    Height(String),
+    /// Alternative text of an image
+    Alt(String),
+
+    /// An attribute with a `null` value which removes the named attribute
+    #[serde(skip)]
+    Null(String),
+    /// An attribute Quill does not define or that has an invalid value
+    #[serde(skip)]
+    Unknown(String, serde_json::Value),
+}
+
+impl Attribute {
+    /// The name of the attribute as used in Quill
+    pub fn name(&self) -> &str {
+        match self {
+            Attribute::Bold(_) => "bold",
+            Attribute::Italic(_) => "italic",
+            Attribute::Underline(_) => "underline",
+            Attribute::Strike(_) => "strike",
+            Attribute::Color(_) => "color",
+            Attribute::Background(_) => "background",
+            Attribute::Font(_) => "font",
+            Attribute::Size(_) => "size",
+            Attribute::Link(_) => "link",
+            Attribute::Script(_) => "script",
+            Attribute::Code(_) => "code",
+            Attribute::Header(_) => "header",
+            Attribute::List(_) => "list",
+            Attribute::CodeBlock(_) => "code-block",
+            Attribute::Blockquote(_) => "blockquote",
+            Attribute::Align(_) => "align",
+            Attribute::Indent(_) => "indent",
+            Attribute::Direction(_) => "direction",
+            Attribute::Width(_) => "width",
+            Attribute::Height(_) => "height",
+            Attribute::Alt(_) => "alt",
+            Attribute::Null(name) | Attribute::Unknown(name, _) => name,
+        }
+    }
+
+    /// Whether the attribute removes the named attribute
+    pub fn is_null(&self) -> bool {
+        matches!(self, Attribute::Null(_))
+    }
+
+    /// Whether the attribute is an inline format of text rather than a format of a line
+    pub fn is_inline(&self) -> bool {
+        matches!(
+            self,
+            Attribute::Bold(_)
+                | Attribute::Italic(_)
+                | Attribute::Underline(_)
+                | Attribute::Strike(_)
+                | Attribute::Color(_)
+                | Attribute::Background(_)
+                | Attribute::Font(_)
+                | Attribute::Size(_)
+                | Attribute::Link(_)
+                | Attribute::Script(_)
+                | Attribute::Code(_)
+        )
+    }
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum RetainType {
+    /// Number of characters to keep
+    Length(usize),
+    /// Changes to an embed keyed by the embed type
+    Embed(serde_json::Map<String, serde_json::Value>),
+}
+
+impl From<usize> for RetainType {
+    fn from(length: usize) -> Self {
+        RetainType::Length(length)
+    }
+}
+
+/// The kind of change an op makes.
+/// Lengths are counted in UTF-16 code units like in Quill, embeds have a length of one.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum Change {
     Insert(DeltaType),
-    Delete(DeltaType),
-    Retain(DeltaType),
+    /// Number of characters to remove
+    Delete(usize),
+    Retain(RetainType),
 }
 
 #[serde_as]
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Op {
     #[serde(flatten)]
     pub change: Change,
-    #[serde_as(as = "Option<EnumMap>")]
+    #[serde_as(as = "Option<AttributeMap>")]
+    #[serde(skip_serializing_if = "attributes::is_empty")]
     pub attributes: Option<Vec<Attribute>>,
 }
 
-#[derive(Deserialize, Debug)]
+/// Ops are equal if they make the same change with the same attributes in any order
+impl PartialEq for Op {
+    fn eq(&self, other: &Self) -> bool {
+        self.change == other.change
+            && attributes::eq(self.attributes.as_deref(), other.attributes.as_deref())
+    }
+}
+
+impl Op {
+    /// Length of the op in UTF-16 code units
+    pub fn length(&self) -> usize {
+        match &self.change {
+            Change::Insert(DeltaType::String(text)) => text.encode_utf16().count(),
+            Change::Delete(length) | Change::Retain(RetainType::Length(length)) => *length,
+            Change::Insert(_) | Change::Retain(RetainType::Embed(_)) => 1,
+        }
+    }
+
+    /// Copy of this op with its insert replaced by the given text
+    fn slice_text(&self, text: &str) -> Op {
+        Op {
+            change: Change::Insert(DeltaType::String(text.to_string())),
+            attributes: self.attributes.clone(),
+        }
+    }
+}
+
+/// A Quill Delta.
+/// It can be parsed from and serialized to the same JSON as used by Quill.
+///
+/// Deserializing fails on attributes and embeds that Quill does not define,
+/// [`Delta::parse_lenient`] keeps them instead.
+#[derive(Serialize, Debug, Clone, PartialEq, Default)]
 pub struct Delta {
     pub ops: Vec<Op>,
 }
 
+/// The JSON of a Delta, deserialized without checking for unknown content
+#[derive(Deserialize)]
+struct LenientDelta {
+    ops: Vec<Op>,
+}
+
+impl<'de> Deserialize<'de> for Delta {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let delta = Delta {
+            ops: LenientDelta::deserialize(deserializer)?.ops,
+        };
+        match delta.warnings().first() {
+            Some(warning) => Err(D::Error::custom(warning)),
+            None => Ok(delta),
+        }
+    }
+}
+
 impl Delta {
     /// Creates an empty Delta
     pub fn new() -> Self {
         Self { ops: Vec::new() }
     }
 
-    /// Add a new Op to the Delta
-    pub fn push(&mut self, op: Op) {
-        self.ops.push(op);
+    /// Add a new Op to the Delta.
+    /// Like in Quill the op is merged with the previous op if they have the same type and
+    /// attributes, and inserts are always placed before a delete at the same position.
+    pub fn push(&mut self, mut op: Op) {
+        if op.attributes.as_ref().is_some_and(Vec::is_empty) {
+            op.attributes = None;
+        }
+
+        let mut index = self.ops.len();
+        if let Some(last) = self.ops.last_mut() {
+            if let (Change::Delete(last_length), Change::Delete(length)) =
+                (&mut last.change, &op.change)
+            {
+                *last_length += length;
+                return;
+            }
+
+            // It does not matter if we insert before or after deleting at the same index,
+            // so always prefer to insert first
+            if matches!(last.change, Change::Delete(_)) && matches!(op.change, Change::Insert(_)) {
+                index -= 1;
+            }
+        }
+
+        if let Some(last) = index.checked_sub(1).map(|last| &mut self.ops[last]) {
+            if attributes::eq(last.attributes.as_deref(), op.attributes.as_deref()) {
+                match (&mut last.change, &op.change) {
+                    (
+                        Change::Insert(DeltaType::String(last_text)),
+                        Change::Insert(DeltaType::String(text)),
+                    ) => {
+                        last_text.push_str(text);
+                        return;
+                    }
+                    (
+                        Change::Retain(RetainType::Length(last_length)),
+                        Change::Retain(RetainType::Length(length)),
+                    ) => {
+                        *last_length += length;
+                        return;
+                    }
+                    _ => (),
+                }
+            }
+        }
+
+        self.ops.insert(index, op);
+    }
+
+    /// Insert text or an embed. Empty text is ignored.
+    pub fn insert(&mut self, insert: impl Into<DeltaType>, attributes: Option<Vec<Attribute>>) {
+        let insert = insert.into();
+        if matches!(&insert, DeltaType::String(text) if text.is_empty()) {
+            return;
+        }
+        self.push(Op {
+            change: Change::Insert(insert),
+            attributes,
+        });
+    }
+
+    /// Delete a number of characters. Deleting nothing is ignored.
+    pub fn delete(&mut self, length: usize) {
+        if length == 0 {
+            return;
+        }
+        self.push(Op {
+            change: Change::Delete(length),
+            attributes: None,
+        });
+    }
+
+    /// Retain a number of characters or an embed. Retaining nothing is ignored.
+    pub fn retain(&mut self, retain: impl Into<RetainType>, attributes: Option<Vec<Attribute>>) {
+        let retain = retain.into();
+        if retain == RetainType::Length(0) {
+            return;
+        }
+        self.push(Op {
+            change: Change::Retain(retain),
+            attributes,
+        });
     }
 
     /// Extend one Delta with another
     pub fn extend(&mut self, other: Delta) {
-        self.ops.extend(other.ops);
+        let mut ops = other.ops.into_iter();
+        if let Some(first) = ops.next() {
+            self.push(first);
+        }
+        self.ops.extend(ops);
+    }
+
+    /// Remove a trailing retain without attributes as it does not change anything
+    pub fn chop(&mut self) {
+        if let Some(Op {
+            change: Change::Retain(RetainType::Length(_)),
+            attributes: None,
+        }) = self.ops.last()
+        {
+            self.ops.pop();
+        }
+    }
+
+    /// Total length of the ops in UTF-16 code units
+    pub fn length(&self) -> usize {
+        self.ops.iter().map(Op::length).sum()
+    }
+
+    /// Get the part of the Delta between two positions in UTF-16 code units
+    pub fn slice(&self, start: usize, end: usize) -> Delta {
+        let mut iter = OpIterator::new(&self.ops);
+        let mut delta = Delta::new();
+        let mut index = 0;
+
+        while index < end && iter.has_next() {
+            let op = if index < start {
+                iter.next(start - index)
+            } else {
+                let op = iter.next(end - index);
+                delta.ops.push(op.clone());
+                op
+            };
+            index += op.length();
+        }
+        delta
+    }
+
+    /// The text of a document Delta with embeds replaced by a null character.
+    /// Returns `None` if the Delta contains anything other than inserts.
+    fn document_text(&self) -> Option<Vec<char>> {
+        let mut text = Vec::new();
+        for op in &self.ops {
+            match &op.change {
+                Change::Insert(DeltaType::String(string)) => text.extend(string.chars()),
+                Change::Insert(_) => text.push('\0'),
+                Change::Delete(_) | Change::Retain(_) => return None,
+            }
+        }
+        Some(text)
+    }
+
+    /// Compose this Delta with another one, returning a Delta that is equivalent to
+    /// applying this Delta followed by `other`.
+    ///
+    /// Attributes of `other` are merged into the ones of this Delta and `null` attributes
+    /// remove them. Changes to embeds through retains with an object are not interpreted,
+    /// the embed is kept as is.
+    pub fn compose(&self, other: &Delta) -> Delta {
+        let mut this_iter = OpIterator::new(&self.ops);
+        let mut other_iter = OpIterator::new(&other.ops);
+        let mut delta = Delta::new();
+
+        // Inserts that are retained as a whole at the start can be copied directly
+        if let Some(Op {
+            change: Change::Retain(RetainType::Length(first_retain)),
+            attributes: None,
+        }) = other_iter.peek()
+        {
+            let mut first_left = *first_retain;
+            while this_iter.peek_type() == OpType::Insert && this_iter.peek_length() <= first_left {
+                first_left -= this_iter.peek_length();
+                delta.ops.push(this_iter.next(usize::MAX));
+            }
+            if first_retain - first_left > 0 {
+                other_iter.next(first_retain - first_left);
+            }
+        }
+
+        while this_iter.has_next() || other_iter.has_next() {
+            if other_iter.peek_type() == OpType::Insert {
+                delta.push(other_iter.next(usize::MAX));
+                continue;
+            }
+            if this_iter.peek_type() == OpType::Delete {
+                delta.push(this_iter.next(usize::MAX));
+                continue;
+            }
+
+            let length = this_iter.peek_length().min(other_iter.peek_length());
+            let this_op = this_iter.next(length);
+            // Taking text can round up to a whole character, other has to keep up with it
+            let length = this_op.length();
+            let other_op = other_iter.next(length);
+
+            match other_op.change {
+                Change::Retain(other_retain) => {
+                    // Removals only need to be kept if they apply to a retain
+                    let keep_null = matches!(this_op.change, Change::Retain(RetainType::Length(_)));
+                    let attributes = attributes::compose(
+                        this_op.attributes.as_deref(),
+                        other_op.attributes.as_deref(),
+                        keep_null,
+                    );
+                    let change = match (this_op.change, other_retain) {
+                        (Change::Retain(RetainType::Length(_)), RetainType::Length(_)) => {
+                            Change::Retain(RetainType::Length(length))
+                        }
+                        (Change::Retain(_), RetainType::Embed(embed)) => {
+                            Change::Retain(RetainType::Embed(embed))
+                        }
+                        (change, _) => change,
+                    };
+
+                    let op = Op { change, attributes };
+                    delta.push(op.clone());
+
+                    // The rest of this Delta can be copied if the rest of other is a retain
+                    if !other_iter.has_next() && delta.ops.last() == Some(&op) {
+                        delta.extend(Delta {
+                            ops: this_iter.rest(),
+                        });
+                        delta.chop();
+                        return delta;
+                    }
+                }
+                // Deleting an insert cancels both out
+                Change::Delete(_) => {
+                    if matches!(this_op.change, Change::Retain(_)) {
+                        delta.push(other_op);
+                    }
+                }
+                Change::Insert(_) => (),
+            }
+        }
+
+        delta.chop();
+        delta
+    }
+
+    /// Transform `other` against this Delta, so that it can be applied after this Delta
+    /// even though both were made on the same document.
+    ///
+    /// `priority` tells whether this Delta is considered to have happened first, which
+    /// decides the order of inserts at the same position and which attributes win.
+    pub fn transform(&self, other: &Delta, priority: bool) -> Delta {
+        let mut this_iter = OpIterator::new(&self.ops);
+        let mut other_iter = OpIterator::new(&other.ops);
+        let mut delta = Delta::new();
+
+        while this_iter.has_next() || other_iter.has_next() {
+            if this_iter.peek_type() == OpType::Insert
+                && (priority || other_iter.peek_type() != OpType::Insert)
+            {
+                delta.retain(this_iter.next(usize::MAX).length(), None);
+                continue;
+            }
+            if other_iter.peek_type() == OpType::Insert {
+                delta.push(other_iter.next(usize::MAX));
+                continue;
+            }
+
+            let length = this_iter.peek_length().min(other_iter.peek_length());
+            let this_op = this_iter.next(length);
+            let other_op = other_iter.next(length);
+
+            // Our delete either makes their delete redundant or removes their retain
+            if matches!(this_op.change, Change::Delete(_)) {
+                continue;
+            }
+
+            match other_op.change {
+                Change::Delete(_) => delta.push(other_op),
+                Change::Retain(retain) => {
+                    let retain = match retain {
+                        RetainType::Length(_) => RetainType::Length(length),
+                        embed => embed,
+                    };
+                    let attributes = attributes::transform(
+                        this_op.attributes.as_deref(),
+                        other_op.attributes.as_deref(),
+                        priority,
+                    );
+                    delta.retain(retain, attributes);
+                }
+                Change::Insert(_) => (),
+            }
+        }
+
+        delta.chop();
+        delta
+    }
+
+    /// Compute the Delta that turns this document into the `other` document,
+    /// so that `self.compose(&self.diff(&other)?) == other`.
+    ///
+    /// Returns `None` if either Delta is not a document, that is contains retains or deletes.
+    pub fn diff(&self, other: &Delta) -> Option<Delta> {
+        let this_text = self.document_text()?;
+        let other_text = other.document_text()?;
+        if self.ops == other.ops {
+            return Some(Delta::new());
+        }
+
+        let mut this_iter = OpIterator::new(&self.ops);
+        let mut other_iter = OpIterator::new(&other.ops);
+        let mut delta = Delta::new();
+        let mut this_index = 0;
+        let mut other_index = 0;
+
+        for (diff_type, chars) in diff::diff(&this_text, &other_text) {
+            // The diff counts characters but ops are measured in UTF-16 code units
+            let text = match diff_type {
+                DiffType::Insert => &other_text[other_index..other_index + chars],
+                DiffType::Delete | DiffType::Equal => &this_text[this_index..this_index + chars],
+            };
+            if diff_type != DiffType::Delete {
+                other_index += chars;
+            }
+            if diff_type != DiffType::Insert {
+                this_index += chars;
+            }
+            let mut length: usize = text.iter().map(|c| c.len_utf16()).sum();
+
+            while length > 0 {
+                let op_length = match diff_type {
+                    DiffType::Insert => {
+                        let op_length = other_iter.peek_length().min(length);
+                        delta.push(other_iter.next(op_length));
+                        op_length
+                    }
+                    DiffType::Delete => {
+                        let op_length = this_iter.peek_length().min(length);
+                        this_iter.next(op_length);
+                        delta.delete(op_length);
+                        op_length
+                    }
+                    DiffType::Equal => {
+                        let op_length = this_iter
+                            .peek_length()
+                            .min(other_iter.peek_length())
+                            .min(length);
+                        let this_op = this_iter.next(op_length);
+                        let other_op = other_iter.next(op_length);
+                        if this_op.change == other_op.change {
+                            let attributes = attributes::diff(
+                                this_op.attributes.as_deref(),
+                                other_op.attributes.as_deref(),
+                            );
+                            delta.retain(op_length, attributes);
+                        } else {
+                            // Different embeds
+                            delta.push(other_op);
+                            delta.delete(op_length);
+                        }
+                        op_length
+                    }
+                };
+                length -= op_length;
+            }
+        }
+
+        delta.chop();
+        Some(delta)
+    }
+
+    /// Compute the Delta that undoes this Delta when applied to the `base` document,
+    /// so that `base.compose(self).compose(&self.invert(base)) == base`.
+    ///
+    /// Changes to embeds through retains with an object can not be undone, only their
+    /// attributes are restored.
+    pub fn invert(&self, base: &Delta) -> Delta {
+        let mut inverted = Delta::new();
+        let mut base_index = 0;
+
+        for op in &self.ops {
+            match &op.change {
+                Change::Insert(_) => inverted.delete(op.length()),
+                Change::Retain(RetainType::Length(length)) if op.attributes.is_none() => {
+                    inverted.retain(*length, None);
+                    base_index += length;
+                }
+                Change::Delete(length) | Change::Retain(RetainType::Length(length)) => {
+                    let slice = base.slice(base_index, base_index + length);
+                    for base_op in slice.ops {
+                        if let Change::Delete(_) = op.change {
+                            inverted.push(base_op);
+                        } else {
+                            let attributes = attributes::invert(
+                                op.attributes.as_deref(),
+                                base_op.attributes.as_deref(),
+                            );
+                            inverted.retain(base_op.length(), attributes);
+                        }
+                    }
+                    base_index += length;
+                }
+                Change::Retain(RetainType::Embed(_)) => {
+                    let slice = base.slice(base_index, base_index + 1);
+                    let base_attributes = slice.ops.first().and_then(|op| op.attributes.as_deref());
+                    let attributes = attributes::invert(op.attributes.as_deref(), base_attributes);
+                    inverted.retain(1, attributes);
+                    base_index += 1;
+                }
+            }
+        }
+
+        inverted.chop();
+        inverted
+    }
+
+    /// Transform a cursor position against this Delta.
+    /// With `priority` an insert at the position itself does not move it.
+    pub fn transform_position(&self, mut index: usize, priority: bool) -> usize {
+        let mut iter = OpIterator::new(&self.ops);
+        let mut offset = 0;
+
+        while iter.has_next() && offset <= index {
+            let length = iter.peek_length();
+            let op_type = iter.peek_type();
+            iter.next(usize::MAX);
+
+            match op_type {
+                OpType::Delete => {
+                    index -= length.min(index - offset);
+                    continue;
+                }
+                OpType::Insert if offset < index || !priority => index += length,
+                _ => (),
+            }
+            offset += length;
+        }
+        index
+    }
+
+    /// Parse a Delta keeping unknown attributes and embeds instead of failing.
+    /// They are listed by [`Delta::warnings`].
+    pub fn parse_lenient(s: &str) -> serde_json::Result<Delta> {
+        let delta: LenientDelta = serde_json::from_str(s)?;
+        Ok(Delta { ops: delta.ops })
+    }
+
+    /// List the attributes and embeds of this Delta that Quill does not define
+    pub fn warnings(&self) -> Vec<ParseWarning> {
+        let mut warnings = Vec::new();
+        for (index, op) in self.ops.iter().enumerate() {
+            if let Change::Insert(DeltaType::Other(embed)) = &op.change {
+                warnings.push(ParseWarning::UnknownEmbed {
+                    op: index,
+                    embed: embed.keys().next().cloned().unwrap_or_default(),
+                });
+            }
+            for attribute in op.attributes.iter().flatten() {
+                if let Attribute::Unknown(name, _) = attribute {
+                    warnings.push(ParseWarning::UnknownAttribute {
+                        op: index,
+                        name: name.clone(),
+                    });
+                }
+            }
+        }
+        warnings
+    }
+
+    /// Iterate over the lines of a document Delta, similar to Quill's `eachLine`.
+    /// Every line yields its inline inserts along with the attributes of the
+    /// newline that terminates it. Retains and deletes are skipped.
+    pub fn lines(&self) -> Lines<'_> {
+        Lines {
+            ops: self.ops.iter().enumerate(),
+            current: None,
+        }
+    }
+}
+
+/// A single line of a document Delta as produced by [`Delta::lines`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Line {
+    /// The inline inserts of the line without the terminating newline,
+    /// each paired with the index of the op it was taken from.
+    pub ops: Vec<(usize, Op)>,
+    /// Attributes of the newline terminating the line.
+    /// These hold the block formats like headers and lists.
+    pub attributes: Option<Vec<Attribute>>,
+    /// Index of the op holding the terminating newline,
+    /// `None` for trailing text without a newline.
+    pub index: Option<usize>,
+}
+
+/// Iterator over the lines of a Delta, see [`Delta::lines`].
+pub struct Lines<'a> {
+    ops: std::iter::Enumerate<std::slice::Iter<'a, Op>>,
+    /// The op that is currently being split with the byte offset of the remaining text
+    current: Option<(usize, &'a Op, usize)>,
+}
+
+impl<'a> Iterator for Lines<'a> {
+    type Item = Line;
+
+    fn next(&mut self) -> Option<Line> {
+        let mut ops = Vec::new();
+
+        loop {
+            let (index, op, offset) = match self.current.take() {
+                Some(current) => current,
+                None => match self.ops.next() {
+                    Some((index, op)) => (index, op, 0),
+                    None => break,
+                },
+            };
+
+            match &op.change {
+                Change::Insert(DeltaType::String(text)) => {
+                    let rest = &text[offset..];
+                    let Some(newline) = rest.find('\n') else {
+                        if !rest.is_empty() {
+                            ops.push((index, op.slice_text(rest)));
+                        }
+                        continue;
+                    };
+
+                    if newline > 0 {
+                        ops.push((index, op.slice_text(&rest[..newline])));
+                    }
+                    if newline + 1 < rest.len() {
+                        self.current = Some((index, op, offset + newline + 1));
+                    }
+                    return Some(Line {
+                        ops,
+                        attributes: op.attributes.clone(),
+                        index: Some(index),
+                    });
+                }
+                Change::Insert(_) => ops.push((index, op.clone())),
+                // Retains and deletes are not part of a document
+                Change::Delete(_) | Change::Retain(_) => (),
+            }
+        }
+
+        // Trailing text that is not terminated by a newline
+        if ops.is_empty() {
+            None
+        } else {
+            Some(Line {
+                ops,
+                attributes: None,
+                index: None,
+            })
+        }
     }
 }
 
+/// Content kept by lenient parsing that is not part of Quill's formats
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseWarning {
+    /// An attribute with an unknown name or an invalid value
+    UnknownAttribute { op: usize, name: String },
+    /// An embed that Quill does not define
+    UnknownEmbed { op: usize, embed: String },
+}
+
+impl fmt::Display for ParseWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseWarning::UnknownAttribute { op, name } => {
+                write!(f, "op {}: unknown attribute `{}`", op, name)
+            }
+            ParseWarning::UnknownEmbed { op, embed } => {
+                write!(f, "op {}: unknown embed `{}`", op, embed)
+            }
+        }
+    }
+}
+
+/// Parse a Delta, failing on attributes and embeds that Quill does not define
 impl FromStr for Delta {
     type Err = serde_json::Error;
 
@@ -78,3 +920,443 @@ impl FromStr for Delta {
         serde_json::from_str(s)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn delta(value: serde_json::Value) -> Delta {
+        serde_json::from_value(value).unwrap()
+    }
+
+    /// The inserts of every line with the attributes of its newline
+    fn lines(delta: &Delta) -> Vec<(Vec<Change>, Option<Vec<Attribute>>)> {
+        delta
+            .lines()
+            .map(|line| {
+                let changes = line.ops.into_iter().map(|(_, op)| op.change).collect();
+                (changes, line.attributes)
+            })
+            .collect()
+    }
+
+    fn text(text: &str) -> Change {
+        Change::Insert(DeltaType::String(text.to_string()))
+    }
+
+    #[test]
+    fn lines_split_newlines_of_one_op() {
+        let delta = delta(json!({"ops": [
+            {"insert": "One"},
+            {"insert": "\n\n", "attributes": {"list": "bullet"}},
+        ]}));
+        let bullet = Some(vec![Attribute::List(ListType::Bullet)]);
+        assert_eq!(
+            lines(&delta),
+            vec![(vec![text("One")], bullet.clone()), (vec![], bullet)]
+        );
+    }
+
+    #[test]
+    fn lines_keep_embeds_before_the_newline() {
+        let delta = delta(json!({"ops": [
+            {"insert": "Look", "attributes": {"bold": true}},
+            {"insert": {"image": "https://example.com/a.png"}},
+            {"insert": "\n", "attributes": {"header": 2}},
+        ]}));
+        let line = delta.lines().next().unwrap();
+        let indices: Vec<usize> = line.ops.iter().map(|(index, _)| *index).collect();
+        assert_eq!(indices, vec![0, 1]);
+        assert!(matches!(
+            line.ops[1].1.change,
+            Change::Insert(DeltaType::Image(_))
+        ));
+        assert_eq!(line.attributes, Some(vec![Attribute::Header(2)]));
+        assert_eq!(delta.lines().count(), 1);
+    }
+
+    #[test]
+    fn lines_keep_trailing_text_without_newline() {
+        let delta = delta(json!({"ops": [
+            {"insert": "First\nSec"},
+            {"insert": "ond", "attributes": {"italic": true}},
+        ]}));
+        assert_eq!(
+            lines(&delta),
+            vec![
+                (vec![text("First")], None),
+                (vec![text("Sec"), text("ond")], None),
+            ]
+        );
+    }
+
+    #[test]
+    fn lines_yield_empty_lines() {
+        let delta = delta(json!({"ops": [
+            {"insert": "\nText\n\n"},
+            {"insert": "\n", "attributes": {"header": 1}},
+        ]}));
+        assert_eq!(
+            lines(&delta),
+            vec![
+                (vec![], None),
+                (vec![text("Text")], None),
+                (vec![], None),
+                (vec![], Some(vec![Attribute::Header(1)])),
+            ]
+        );
+    }
+
+    #[test]
+    fn compose_insert_and_retain_with_attributes() {
+        let a = delta(json!({"ops": [{"insert": "Hello"}]}));
+        let b = delta(json!({"ops": [{"retain": 2}, {"retain": 3, "attributes": {"bold": true}}]}));
+        let expected = delta(json!({"ops": [
+            {"insert": "He"},
+            {"insert": "llo", "attributes": {"bold": true}},
+        ]}));
+        assert_eq!(a.compose(&b), expected);
+    }
+
+    #[test]
+    fn compose_null_attribute_removes_value() {
+        let a = delta(json!({"ops": [
+            {"insert": "Hello", "attributes": {"bold": true, "italic": true}},
+        ]}));
+        let b = delta(json!({"ops": [{"retain": 5, "attributes": {"bold": null}}]}));
+        let expected = delta(json!({"ops": [
+            {"insert": "Hello", "attributes": {"italic": true}},
+        ]}));
+        assert_eq!(a.compose(&b), expected);
+
+        // A removal on a retain is kept so it still applies to the document
+        let a = delta(json!({"ops": [{"retain": 5, "attributes": {"bold": true}}]}));
+        assert_eq!(a.compose(&b), b);
+    }
+
+    #[test]
+    fn compose_delete_of_insert() {
+        let a = delta(json!({"ops": [{"insert": "Hello"}]}));
+        let b = delta(json!({"ops": [{"retain": 1}, {"delete": 3}]}));
+        assert_eq!(a.compose(&b), delta(json!({"ops": [{"insert": "Ho"}]})));
+
+        let b = delta(json!({"ops": [{"delete": 5}]}));
+        assert_eq!(a.compose(&b), Delta::new());
+    }
+
+    #[test]
+    fn compose_splits_surrogate_pairs_across_ops() {
+        // Every emoji is two UTF-16 code units long
+        let a = delta(json!({"ops": [{"insert": "x😀y😀z"}]}));
+        let b = delta(json!({"ops": [
+            {"retain": 1},
+            {"retain": 2, "attributes": {"bold": true}},
+            {"delete": 1},
+            {"retain": 2, "attributes": {"italic": true}},
+        ]}));
+        let expected = delta(json!({"ops": [
+            {"insert": "x"},
+            {"insert": "😀", "attributes": {"bold": true}},
+            {"insert": "😀", "attributes": {"italic": true}},
+            {"insert": "z"},
+        ]}));
+        assert_eq!(a.compose(&b), expected);
+        assert_eq!(a.compose(&b).length(), 6);
+    }
+
+    #[test]
+    fn compose_rounds_splits_inside_surrogate_pairs_up() {
+        let a = delta(json!({"ops": [{"insert": "a😀b"}]}));
+        let b = delta(json!({"ops": [{"retain": 2}, {"retain": 1, "attributes": {"bold": true}}]}));
+        let expected = delta(json!({"ops": [
+            {"insert": "a😀"},
+            {"insert": "b", "attributes": {"bold": true}},
+        ]}));
+        assert_eq!(a.compose(&b), expected);
+        assert_eq!(a.compose(&b).length(), 4);
+
+        // Deleting half of the emoji deletes all of it
+        let b = delta(json!({"ops": [{"retain": 1}, {"delete": 1}]}));
+        assert_eq!(a.compose(&b), delta(json!({"ops": [{"insert": "ab"}]})));
+    }
+
+    #[test]
+    fn compose_first_retain_copies_inserts() {
+        let a = delta(json!({"ops": [
+            {"insert": "Hello"},
+            {"insert": " World", "attributes": {"bold": true}},
+        ]}));
+        let b = delta(json!({"ops": [{"retain": 11}, {"insert": "!"}]}));
+        let expected = delta(json!({"ops": [
+            {"insert": "Hello"},
+            {"insert": " World", "attributes": {"bold": true}},
+            {"insert": "!"},
+        ]}));
+        assert_eq!(a.compose(&b), expected);
+
+        // Only inserts that are retained as a whole are copied
+        let b = delta(json!({"ops": [{"retain": 7}, {"delete": 4}]}));
+        let expected = delta(json!({"ops": [
+            {"insert": "Hello"},
+            {"insert": " W", "attributes": {"bold": true}},
+        ]}));
+        assert_eq!(a.compose(&b), expected);
+    }
+
+    /// Both orders of applying concurrent changes end in the same document
+    fn assert_converges(a: &Delta, b: &Delta) {
+        assert_eq!(
+            a.compose(&a.transform(b, true)),
+            b.compose(&b.transform(a, false))
+        );
+    }
+
+    #[test]
+    fn transform_inserts_at_same_index() {
+        let a = delta(json!({"ops": [{"retain": 2}, {"insert": "A"}]}));
+        let b = delta(json!({"ops": [{"retain": 2}, {"insert": "B"}]}));
+        assert_eq!(
+            a.transform(&b, true),
+            delta(json!({"ops": [{"retain": 3}, {"insert": "B"}]}))
+        );
+        assert_eq!(a.transform(&b, false), b);
+        assert_converges(&a, &b);
+    }
+
+    #[test]
+    fn transform_overlapping_deletes() {
+        let a = delta(json!({"ops": [{"retain": 1}, {"delete": 3}]}));
+        let b = delta(json!({"ops": [{"retain": 2}, {"delete": 3}]}));
+        assert_eq!(
+            a.transform(&b, true),
+            delta(json!({"ops": [{"retain": 1}, {"delete": 1}]}))
+        );
+        assert_converges(&a, &b);
+    }
+
+    #[test]
+    fn transform_conflicting_attributes() {
+        let a = delta(json!({"ops": [
+            {"retain": 3, "attributes": {"bold": true, "header": 1}},
+        ]}));
+        let b = delta(json!({"ops": [
+            {"retain": 3, "attributes": {"header": 2, "italic": true}},
+        ]}));
+        assert_eq!(
+            a.transform(&b, true),
+            delta(json!({"ops": [{"retain": 3, "attributes": {"italic": true}}]}))
+        );
+        assert_eq!(a.transform(&b, false), b);
+
+        let b = delta(json!({"ops": [
+            {"retain": 3, "attributes": {"bold": false, "header": 2}},
+        ]}));
+        assert_converges(&a, &b);
+    }
+
+    #[test]
+    fn transform_position_with_priority() {
+        let a = delta(json!({"ops": [{"retain": 2}, {"insert": "ab"}]}));
+        assert_eq!(a.transform_position(2, true), 2);
+        assert_eq!(a.transform_position(3, true), 5);
+
+        let a = delta(json!({"ops": [{"insert": "xy"}]}));
+        assert_eq!(a.transform_position(0, true), 0);
+    }
+
+    #[test]
+    fn transform_position_without_priority() {
+        let a = delta(json!({"ops": [{"retain": 2}, {"insert": "ab"}]}));
+        assert_eq!(a.transform_position(1, false), 1);
+        assert_eq!(a.transform_position(2, false), 4);
+
+        // Deletes move the position back, at most to where they start
+        let a = delta(json!({"ops": [{"retain": 1}, {"delete": 2}]}));
+        assert_eq!(a.transform_position(4, false), 2);
+        assert_eq!(a.transform_position(2, false), 1);
+    }
+
+    /// A document with plain text, empty text has no ops
+    fn document(text: &str) -> Delta {
+        let mut delta = Delta::new();
+        delta.insert(text, None);
+        delta
+    }
+
+    /// Assert that the diff between two documents turns the first into the second
+    fn assert_diff_round_trips(a: &Delta, b: &Delta) {
+        let diff = a.diff(b).unwrap();
+        assert_eq!(a.compose(&diff), *b, "diff {:?}", diff);
+    }
+
+    #[test]
+    fn diff_round_trips_text() {
+        let pairs = [
+            ("", "Hello\n"),
+            ("Hello\n", ""),
+            ("Hello World\n", "Hello big World\n"),
+            ("The quick brown fox\n", "A quick red fox jumps\n"),
+            ("abcabba\n", "cbabac\n"),
+        ];
+        for (a, b) in pairs {
+            let (a, b) = (document(a), document(b));
+            assert_diff_round_trips(&a, &b);
+            assert_diff_round_trips(&b, &a);
+        }
+    }
+
+    #[test]
+    fn diff_round_trips_attributes_embeds_and_non_bmp() {
+        let a = delta(json!({"ops": [
+            {"insert": "Smile 😀 at "},
+            {"insert": "𝒳", "attributes": {"italic": true}},
+            {"insert": {"image": "https://example.com/a.png"}},
+            {"insert": "\n"},
+        ]}));
+        let b = delta(json!({"ops": [
+            {"insert": "Smile 🙂 at ", "attributes": {"bold": true}},
+            {"insert": "𝒳𝒴"},
+            {"insert": {"image": "https://example.com/b.png"}},
+            {"insert": "😀\n"},
+        ]}));
+        assert_diff_round_trips(&a, &b);
+        assert_diff_round_trips(&b, &a);
+        assert_eq!(a.diff(&a), Some(Delta::new()));
+
+        // Only documents can be diffed
+        let change = delta(json!({"ops": [{"retain": 1}]}));
+        assert_eq!(a.diff(&change), None);
+    }
+
+    #[test]
+    fn invert_undoes_change() {
+        let base = delta(json!({"ops": [
+            {"insert": "Hello "},
+            {"insert": "World", "attributes": {"bold": true}},
+            {"insert": {"image": "https://example.com/a.png"}},
+            {"insert": "😀\n"},
+        ]}));
+        let changes = [
+            json!({"ops": [{"retain": 6}, {"insert": "big "}]}),
+            json!({"ops": [{"retain": 3}, {"delete": 6}]}),
+            json!({"ops": [{"retain": 6, "attributes": {"italic": true}}, {"retain": 5, "attributes": {"bold": null}}]}),
+            json!({"ops": [{"retain": 11}, {"delete": 1}, {"retain": 2, "attributes": {"italic": true}}]}),
+        ];
+        for change in changes {
+            let change = delta(change);
+            let inverted = change.invert(&base);
+            assert_eq!(
+                base.compose(&change).compose(&inverted),
+                base,
+                "{:?}",
+                change
+            );
+        }
+    }
+
+    /// Assert that parsing and serializing gives back the exact JSON
+    fn assert_json_round_trips(json: &str) {
+        let delta: Delta = json.parse().unwrap();
+        assert_eq!(serde_json::to_string(&delta).unwrap(), json);
+    }
+
+    #[test]
+    fn serialize_like_quill() {
+        assert_json_round_trips(
+            r#"{"ops":[{"insert":"Hello "},{"insert":"World","attributes":{"bold":true,"italic":true}},{"insert":"\n","attributes":{"header":1}}]}"#,
+        );
+        assert_json_round_trips(
+            r#"{"ops":[{"insert":{"image":"https://example.com/a.png"}},{"insert":"\n","attributes":{"list":"ordered"}}]}"#,
+        );
+        assert_json_round_trips(
+            r#"{"ops":[{"retain":5,"attributes":{"bold":null,"italic":true}},{"delete":3},{"insert":"!"}]}"#,
+        );
+        assert_json_round_trips(
+            r#"{"ops":[{"retain":{"image":"https://example.com/b.png"},"attributes":{"bold":null}}]}"#,
+        );
+    }
+
+    #[test]
+    fn serialize_omits_empty_attributes() {
+        let delta = Delta {
+            ops: vec![
+                Op {
+                    change: Change::Insert("a".into()),
+                    attributes: Some(Vec::new()),
+                },
+                Op {
+                    change: Change::Retain(1.into()),
+                    attributes: None,
+                },
+            ],
+        };
+        assert_eq!(
+            serde_json::to_string(&delta).unwrap(),
+            r#"{"ops":[{"insert":"a"},{"retain":1}]}"#
+        );
+
+        let delta: Delta = r#"{"ops":[{"insert":"a","attributes":{}}]}"#.parse().unwrap();
+        assert_eq!(
+            serde_json::to_string(&delta).unwrap(),
+            r#"{"ops":[{"insert":"a"}]}"#
+        );
+    }
+
+    #[test]
+    fn serialize_block_and_size_values_like_quill() {
+        assert_json_round_trips(
+            r#"{"ops":[{"insert":"let a;"},{"insert":"\n","attributes":{"code-block":true}},{"insert":"let b;"},{"insert":"\n","attributes":{"code-block":"javascript"}}]}"#,
+        );
+        assert_json_round_trips(
+            r#"{"ops":[{"insert":"a","attributes":{"size":"large"}},{"insert":"b","attributes":{"size":"18px"}}]}"#,
+        );
+    }
+
+    #[test]
+    fn parse_embeds_with_their_formats() {
+        let json = r#"{"ops":[{"insert":{"image":"https://example.com/a.png"},"attributes":{"width":"200","height":"100","alt":"A cat"}},{"insert":{"video":"https://example.com/embed/a"},"attributes":{"width":"640"}},{"insert":{"formula":"e=mc^2"}},{"insert":"\n"}]}"#;
+        assert_json_round_trips(json);
+
+        let delta: Delta = json.parse().unwrap();
+        assert_eq!(
+            delta.ops[0].attributes,
+            Some(vec![
+                Attribute::Width("200".into()),
+                Attribute::Height("100".into()),
+                Attribute::Alt("A cat".into()),
+            ])
+        );
+        assert!(matches!(
+            delta.ops[1].change,
+            Change::Insert(DeltaType::Video(_))
+        ));
+        assert_eq!(
+            delta.ops[2].change,
+            Change::Insert(DeltaType::Formula(Formula {
+                formula: "e=mc^2".into()
+            }))
+        );
+    }
+
+    #[test]
+    fn malformed_embeds_are_an_error() {
+        let embeds = [
+            json!({"image": "not a url"}),
+            json!({"video": "not a url"}),
+            json!({"formula": 1}),
+        ];
+        for embed in embeds {
+            let json = json!({"ops": [{"insert": embed}]}).to_string();
+            assert!(Delta::parse_lenient(&json).is_err(), "{}", json);
+            assert!(json.parse::<Delta>().is_err(), "{}", json);
+        }
+    }
+
+    #[test]
+    fn deserialize_rejects_unknown_content() {
+        let json = r#"{"ops": [{"insert": {"mention": "@quill"}}, {"insert": "a", "attributes": {"glow": 1}}]}"#;
+        assert!(serde_json::from_str::<Delta>(json).is_err());
+        assert_eq!(Delta::parse_lenient(json).unwrap().warnings().len(), 2);
+    }
+}
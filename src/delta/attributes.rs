@@ -0,0 +1,199 @@
+//! Operations on attribute lists, mirroring Quill's `AttributeMap`.
+
+use std::fmt;
+
+use serde::{
+    de::{MapAccess, Visitor},
+    ser::{self, SerializeMap},
+    Deserialize, Deserializer, Serializer,
+};
+use serde_json::{Map, Value};
+use serde_with::{DeserializeAs, SerializeAs};
+
+use super::Attribute;
+
+/// Serde adapter for attributes stored as a JSON object like in Quill.
+/// A `null` value is kept as [`Attribute::Null`] which removes the attribute.
+/// Values that do not form a known attribute are kept as [`Attribute::Unknown`].
+pub(crate) struct AttributeMap;
+
+impl SerializeAs<Vec<Attribute>> for AttributeMap {
+    fn serialize_as<S>(attributes: &Vec<Attribute>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(attributes.len()))?;
+        for attribute in attributes {
+            match attribute {
+                Attribute::Null(name) => {
+                    map.serialize_entry(name, &())?;
+                    continue;
+                }
+                Attribute::Unknown(name, value) => {
+                    map.serialize_entry(name, value)?;
+                    continue;
+                }
+                _ => (),
+            }
+
+            // Attributes serialize to an object with a single entry
+            let value = serde_json::to_value(attribute).map_err(ser::Error::custom)?;
+            if let Value::Object(entry) = value {
+                for (name, value) in entry {
+                    map.serialize_entry(&name, &value)?;
+                }
+            }
+        }
+        map.end()
+    }
+}
+
+impl<'de> DeserializeAs<'de, Vec<Attribute>> for AttributeMap {
+    fn deserialize_as<D>(deserializer: D) -> Result<Vec<Attribute>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(AttributeMapVisitor)
+    }
+}
+
+struct AttributeMapVisitor;
+
+impl<'de> Visitor<'de> for AttributeMapVisitor {
+    type Value = Vec<Attribute>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a map of attributes")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut attributes = Vec::new();
+        while let Some((name, value)) = map.next_entry::<String, Value>()? {
+            if value.is_null() {
+                attributes.push(Attribute::Null(name));
+                continue;
+            }
+
+            let entry = Value::Object(Map::from_iter([(name.clone(), value.clone())]));
+            let attribute =
+                Attribute::deserialize(entry).unwrap_or(Attribute::Unknown(name, value));
+            attributes.push(attribute);
+        }
+        Ok(attributes)
+    }
+}
+
+/// Attributes are left out of the JSON if there are none, like in Quill
+pub(crate) fn is_empty(attributes: &Option<Vec<Attribute>>) -> bool {
+    attributes.as_deref().unwrap_or_default().is_empty()
+}
+
+fn get<'a>(attributes: &'a [Attribute], name: &str) -> Option<&'a Attribute> {
+    attributes.iter().find(|attribute| attribute.name() == name)
+}
+
+fn non_empty(attributes: Vec<Attribute>) -> Option<Vec<Attribute>> {
+    (!attributes.is_empty()).then_some(attributes)
+}
+
+/// Compare two attribute lists ignoring their order
+pub(crate) fn eq(a: Option<&[Attribute]>, b: Option<&[Attribute]>) -> bool {
+    let a = a.unwrap_or_default();
+    let b = b.unwrap_or_default();
+    a.len() == b.len() && a.iter().all(|attribute| b.contains(attribute))
+}
+
+/// Apply the attributes `b` on top of `a`.
+/// Removals in `b` are dropped unless `keep_null` is set.
+pub(crate) fn compose(
+    a: Option<&[Attribute]>,
+    b: Option<&[Attribute]>,
+    keep_null: bool,
+) -> Option<Vec<Attribute>> {
+    let a = a.unwrap_or_default();
+    let b = b.unwrap_or_default();
+
+    let mut attributes: Vec<Attribute> = b
+        .iter()
+        .filter(|attribute| keep_null || !attribute.is_null())
+        .cloned()
+        .collect();
+    for attribute in a {
+        if get(b, attribute.name()).is_none() {
+            attributes.push(attribute.clone());
+        }
+    }
+    non_empty(attributes)
+}
+
+/// Transform the attributes `b` against `a` that were applied at the same time.
+/// With `priority` the attributes of `a` win.
+pub(crate) fn transform(
+    a: Option<&[Attribute]>,
+    b: Option<&[Attribute]>,
+    priority: bool,
+) -> Option<Vec<Attribute>> {
+    let Some(a) = a else {
+        return b.map(<[Attribute]>::to_vec);
+    };
+    let b = b?;
+    if !priority {
+        // b simply overwrites a without priority
+        return Some(b.to_vec());
+    }
+
+    let attributes = b
+        .iter()
+        .filter(|attribute| get(a, attribute.name()).is_none())
+        .cloned()
+        .collect();
+    non_empty(attributes)
+}
+
+/// Attributes that turn `a` into `b`
+pub(crate) fn diff(a: Option<&[Attribute]>, b: Option<&[Attribute]>) -> Option<Vec<Attribute>> {
+    let a = a.unwrap_or_default();
+    let b = b.unwrap_or_default();
+
+    let mut attributes = Vec::new();
+    for attribute in a {
+        match get(b, attribute.name()) {
+            Some(other) if other == attribute => (),
+            Some(other) => attributes.push(other.clone()),
+            None => attributes.push(Attribute::Null(attribute.name().to_string())),
+        }
+    }
+    for attribute in b {
+        if get(a, attribute.name()).is_none() {
+            attributes.push(attribute.clone());
+        }
+    }
+    non_empty(attributes)
+}
+
+/// Attributes that undo applying `attributes` on top of `base`
+pub(crate) fn invert(
+    attributes: Option<&[Attribute]>,
+    base: Option<&[Attribute]>,
+) -> Option<Vec<Attribute>> {
+    let attributes = attributes.unwrap_or_default();
+    let base = base.unwrap_or_default();
+
+    let mut inverted = Vec::new();
+    for base_attribute in base {
+        if get(attributes, base_attribute.name())
+            .is_some_and(|attribute| attribute != base_attribute)
+        {
+            inverted.push(base_attribute.clone());
+        }
+    }
+    for attribute in attributes {
+        if get(base, attribute.name()).is_none() {
+            inverted.push(Attribute::Null(attribute.name().to_string()));
+        }
+    }
+    non_empty(inverted)
+}
@@ -0,0 +1,179 @@
+//! Character based text diff used by `Delta::diff`.
+//!
+//! This is Myers' algorithm with the linear space bisection used by Quill's `fast-diff`.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum DiffType {
+    Equal,
+    Insert,
+    Delete,
+}
+
+/// Compute the changes that turn `a` into `b`.
+/// Every change is returned with the number of characters it spans.
+pub(crate) fn diff(a: &[char], b: &[char]) -> Vec<(DiffType, usize)> {
+    let mut changes = Vec::new();
+    diff_main(a, b, &mut changes);
+    changes
+}
+
+fn push(changes: &mut Vec<(DiffType, usize)>, diff_type: DiffType, length: usize) {
+    if length == 0 {
+        return;
+    }
+    match changes.last_mut() {
+        Some((last_type, last_length)) if *last_type == diff_type => *last_length += length,
+        _ => changes.push((diff_type, length)),
+    }
+}
+
+fn diff_main(a: &[char], b: &[char], changes: &mut Vec<(DiffType, usize)>) {
+    let prefix = a.iter().zip(b).take_while(|(a, b)| a == b).count();
+    let (a, b) = (&a[prefix..], &b[prefix..]);
+    let suffix = a
+        .iter()
+        .rev()
+        .zip(b.iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (a, b) = (&a[..a.len() - suffix], &b[..b.len() - suffix]);
+
+    push(changes, DiffType::Equal, prefix);
+    diff_compute(a, b, changes);
+    push(changes, DiffType::Equal, suffix);
+}
+
+/// Diff two texts that do not share a common prefix or suffix
+fn diff_compute(a: &[char], b: &[char], changes: &mut Vec<(DiffType, usize)>) {
+    if a.is_empty() || b.is_empty() {
+        push(changes, DiffType::Delete, a.len());
+        push(changes, DiffType::Insert, b.len());
+        return;
+    }
+
+    // The shorter text may be contained in the longer one
+    let (long, short, outer) = if a.len() > b.len() {
+        (a, b, DiffType::Delete)
+    } else {
+        (b, a, DiffType::Insert)
+    };
+    if let Some(start) = long.windows(short.len()).position(|window| window == short) {
+        push(changes, outer, start);
+        push(changes, DiffType::Equal, short.len());
+        push(changes, outer, long.len() - start - short.len());
+        return;
+    }
+
+    // A single character that is not contained in the other text
+    if short.len() == 1 {
+        push(changes, DiffType::Delete, a.len());
+        push(changes, DiffType::Insert, b.len());
+        return;
+    }
+
+    bisect(a, b, changes);
+}
+
+/// Find the middle snake of the diff and split the problem in two there
+fn bisect(a: &[char], b: &[char], changes: &mut Vec<(DiffType, usize)>) {
+    let a_length = a.len() as isize;
+    let b_length = b.len() as isize;
+    let max_d = (a_length + b_length + 1) / 2;
+    let v_offset = max_d;
+    let v_length = 2 * max_d;
+    let mut v1 = vec![-1; v_length as usize];
+    let mut v2 = vec![-1; v_length as usize];
+    v1[(v_offset + 1) as usize] = 0;
+    v2[(v_offset + 1) as usize] = 0;
+
+    let delta = a_length - b_length;
+    // If the total number of characters is odd, the front path will collide with the reverse path
+    let front = delta % 2 != 0;
+    // Offsets for the start and end of the k loops, to prevent mapping of space beyond the grid
+    let mut k1_start = 0;
+    let mut k1_end = 0;
+    let mut k2_start = 0;
+    let mut k2_end = 0;
+
+    for d in 0..max_d {
+        // Walk the front path one step
+        let mut k1 = -d + k1_start;
+        while k1 <= d - k1_end {
+            let k1_offset = (v_offset + k1) as usize;
+            let mut x1 = if k1 == -d || (k1 != d && v1[k1_offset - 1] < v1[k1_offset + 1]) {
+                v1[k1_offset + 1]
+            } else {
+                v1[k1_offset - 1] + 1
+            };
+            let mut y1 = x1 - k1;
+            while x1 < a_length && y1 < b_length && a[x1 as usize] == b[y1 as usize] {
+                x1 += 1;
+                y1 += 1;
+            }
+            v1[k1_offset] = x1;
+
+            if x1 > a_length {
+                // Ran off the right of the graph
+                k1_end += 2;
+            } else if y1 > b_length {
+                // Ran off the bottom of the graph
+                k1_start += 2;
+            } else if front {
+                let k2_offset = v_offset + delta - k1;
+                if (0..v_length).contains(&k2_offset) && v2[k2_offset as usize] != -1 {
+                    // Mirror x2 onto the top-left coordinate system
+                    let x2 = a_length - v2[k2_offset as usize];
+                    if x1 >= x2 {
+                        return split(a, b, x1 as usize, y1 as usize, changes);
+                    }
+                }
+            }
+            k1 += 2;
+        }
+
+        // Walk the reverse path one step
+        let mut k2 = -d + k2_start;
+        while k2 <= d - k2_end {
+            let k2_offset = (v_offset + k2) as usize;
+            let mut x2 = if k2 == -d || (k2 != d && v2[k2_offset - 1] < v2[k2_offset + 1]) {
+                v2[k2_offset + 1]
+            } else {
+                v2[k2_offset - 1] + 1
+            };
+            let mut y2 = x2 - k2;
+            while x2 < a_length
+                && y2 < b_length
+                && a[(a_length - x2 - 1) as usize] == b[(b_length - y2 - 1) as usize]
+            {
+                x2 += 1;
+                y2 += 1;
+            }
+            v2[k2_offset] = x2;
+
+            if x2 > a_length {
+                k2_end += 2;
+            } else if y2 > b_length {
+                k2_start += 2;
+            } else if !front {
+                let k1_offset = v_offset + delta - k2;
+                if (0..v_length).contains(&k1_offset) && v1[k1_offset as usize] != -1 {
+                    let x1 = v1[k1_offset as usize];
+                    let y1 = v_offset + x1 - k1_offset;
+                    if x1 >= a_length - x2 {
+                        return split(a, b, x1 as usize, y1 as usize, changes);
+                    }
+                }
+            }
+            k2 += 2;
+        }
+    }
+
+    // The number of changes equals the number of characters, there is no common subsequence
+    push(changes, DiffType::Delete, a.len());
+    push(changes, DiffType::Insert, b.len());
+}
+
+fn split(a: &[char], b: &[char], x: usize, y: usize, changes: &mut Vec<(DiffType, usize)>) {
+    diff_main(&a[..x], &b[..y], changes);
+    diff_main(&a[x..], &b[y..], changes);
+}
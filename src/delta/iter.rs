@@ -0,0 +1,126 @@
+//! Iterator taking ops of a Delta piece by piece, mirroring Quill's `OpIterator`.
+
+use super::{Change, DeltaType, Op, RetainType};
+
+/// The kind of op an [`OpIterator`] is positioned at
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum OpType {
+    Insert,
+    Delete,
+    Retain,
+}
+
+pub(crate) struct OpIterator<'a> {
+    ops: &'a [Op],
+    index: usize,
+    /// Number of UTF-16 code units already taken from the current op
+    offset: usize,
+}
+
+impl<'a> OpIterator<'a> {
+    pub(crate) fn new(ops: &'a [Op]) -> Self {
+        Self {
+            ops,
+            index: 0,
+            offset: 0,
+        }
+    }
+
+    pub(crate) fn has_next(&self) -> bool {
+        self.index < self.ops.len()
+    }
+
+    pub(crate) fn peek(&self) -> Option<&'a Op> {
+        self.ops.get(self.index)
+    }
+
+    /// Remaining length of the current op.
+    /// Once all ops are taken the iterator behaves like an endless retain.
+    pub(crate) fn peek_length(&self) -> usize {
+        self.peek()
+            .map_or(usize::MAX, |op| op.length() - self.offset)
+    }
+
+    pub(crate) fn peek_type(&self) -> OpType {
+        match self.peek().map(|op| &op.change) {
+            Some(Change::Insert(_)) => OpType::Insert,
+            Some(Change::Delete(_)) => OpType::Delete,
+            Some(Change::Retain(_)) | None => OpType::Retain,
+        }
+    }
+
+    /// Take at most `length` units of the current op.
+    /// Text is never split inside a surrogate pair, a length ending in one takes the whole
+    /// character, so the returned op can be one unit longer than asked for.
+    pub(crate) fn next(&mut self, length: usize) -> Op {
+        let Some(op) = self.peek() else {
+            return Op {
+                change: Change::Retain(RetainType::Length(length)),
+                attributes: None,
+            };
+        };
+
+        let offset = self.offset;
+        let remaining = op.length() - offset;
+        let mut length = length.min(remaining);
+        let change = match &op.change {
+            Change::Delete(_) => Change::Delete(length),
+            Change::Retain(RetainType::Length(_)) => Change::Retain(RetainType::Length(length)),
+            Change::Insert(DeltaType::String(text)) => {
+                let text = utf16_slice(text, offset, length);
+                length = text.encode_utf16().count();
+                Change::Insert(DeltaType::String(text.to_string()))
+            }
+            // Embeds have a length of one and are never split
+            change => change.clone(),
+        };
+
+        if length == remaining {
+            self.index += 1;
+            self.offset = 0;
+        } else {
+            self.offset += length;
+        }
+
+        let attributes = match change {
+            Change::Delete(_) => None,
+            _ => op.attributes.clone(),
+        };
+        Op { change, attributes }
+    }
+
+    /// Take all remaining ops
+    pub(crate) fn rest(&mut self) -> Vec<Op> {
+        if !self.has_next() {
+            return Vec::new();
+        }
+
+        let mut rest = Vec::new();
+        if self.offset > 0 {
+            rest.push(self.next(usize::MAX));
+        }
+        rest.extend_from_slice(&self.ops[self.index..]);
+        self.index = self.ops.len();
+        rest
+    }
+}
+
+/// Byte offset of the character starting at the given UTF-16 offset
+fn utf16_to_byte_offset(text: &str, offset: usize) -> usize {
+    let mut units = 0;
+    for (index, c) in text.char_indices() {
+        if units >= offset {
+            return index;
+        }
+        units += c.len_utf16();
+    }
+    text.len()
+}
+
+/// Get `length` UTF-16 code units of the text starting at `start`.
+/// Both ends are moved forward to the next character if they fall inside a surrogate pair.
+pub(crate) fn utf16_slice(text: &str, start: usize, length: usize) -> &str {
+    let start_byte = utf16_to_byte_offset(text, start);
+    let end_byte = start_byte + utf16_to_byte_offset(&text[start_byte..], length);
+    &text[start_byte..end_byte]
+}
@@ -0,0 +1,426 @@
+//! Small syntax highlighter for code blocks.
+//!
+//! The languages are the ones offered by Quill's syntax module plus a few common ones.
+//! Tokens are found with a simple lexer that knows about comments, strings, numbers and
+//! keywords, which is enough to colour code without pulling in a full grammar engine.
+
+use std::ops::Range;
+
+use genpdf::style::Color;
+
+use crate::paragraph::Run;
+use crate::theme::SyntaxColors;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Token {
+    Keyword,
+    String,
+    Number,
+    Comment,
+    Addition,
+    Deletion,
+}
+
+/// How to find the tokens of a language
+struct Language {
+    /// Names of the language as used by Quill and their common aliases
+    names: &'static [&'static str],
+    /// Keywords separated by whitespace
+    keywords: &'static str,
+    /// Keywords match regardless of case like in SQL
+    ignore_case: bool,
+    line_comments: &'static [&'static str],
+    block_comment: Option<(&'static str, &'static str)>,
+    quotes: &'static [char],
+    /// Names following `<` or `</` are highlighted as keywords like in HTML
+    tags: bool,
+}
+
+const C_KEYWORDS: &str = "\
+    auto bool break case char class const constexpr continue default delete do double else \
+    enum explicit extern false float for friend goto if include inline int long namespace new \
+    nullptr operator private protected public return short signed sizeof static struct switch \
+    template this throw true try typedef typename union unsigned using virtual void volatile \
+    while";
+
+const CS_KEYWORDS: &str = "\
+    abstract as async await base bool break case catch char class const continue decimal \
+    default do double else enum false finally float for foreach if in int interface internal \
+    is long namespace new null object out override private protected public readonly ref \
+    return static string struct switch this throw true try typeof using var virtual void while";
+
+const JAVA_KEYWORDS: &str = "\
+    abstract boolean break byte case catch char class const continue default do double else \
+    enum extends false final finally float for if implements import instanceof int interface \
+    long new null package private protected public return short static super switch \
+    synchronized this throw throws true try var void volatile while";
+
+const JS_KEYWORDS: &str = "\
+    as async await break case catch class const continue default delete do else enum export \
+    extends false finally for from function if implements import in instanceof interface let \
+    new null of return static super switch this throw true try type typeof undefined var void \
+    while yield";
+
+const PHP_KEYWORDS: &str = "\
+    abstract array as break case catch class const continue default do echo else elseif \
+    extends false final finally fn for foreach function if implements include interface isset \
+    list namespace new null private protected public require return static switch throw trait \
+    true try use while";
+
+const PYTHON_KEYWORDS: &str = "\
+    False None True and as assert async await break class continue def del elif else except \
+    finally for from global if import in is lambda nonlocal not or pass raise return self try \
+    while with yield";
+
+const RUBY_KEYWORDS: &str = "\
+    alias and begin break case class def do else elsif end ensure false for if in module next \
+    nil not or redo require rescue retry return self super then true undef unless until when \
+    while yield";
+
+const RUST_KEYWORDS: &str = "\
+    as async await break const continue crate dyn else enum extern false fn for if impl in let \
+    loop match mod move mut pub ref return self Self static struct super trait true type \
+    unsafe use where while";
+
+const GO_KEYWORDS: &str = "\
+    break case chan const continue default defer else fallthrough false for func go goto if \
+    import interface map nil package range return select struct switch true type var";
+
+const BASH_KEYWORDS: &str = "\
+    case do done echo elif else esac exit export fi for function if in local read return then \
+    until while";
+
+const SQL_KEYWORDS: &str = "\
+    add all alter and as asc between by case create delete desc distinct drop else end exists \
+    from group having in index inner insert into is join left like limit not null on or order \
+    outer primary key right select set table then union update values when where";
+
+const LANGUAGES: &[Language] = &[
+    Language {
+        names: &["bash", "sh", "shell"],
+        keywords: BASH_KEYWORDS,
+        ignore_case: false,
+        line_comments: &["#"],
+        block_comment: None,
+        quotes: &['"', '\''],
+        tags: false,
+    },
+    Language {
+        names: &["cpp", "c", "c++", "h"],
+        keywords: C_KEYWORDS,
+        ignore_case: false,
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '\''],
+        tags: false,
+    },
+    Language {
+        names: &["cs", "csharp", "c#"],
+        keywords: CS_KEYWORDS,
+        ignore_case: false,
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '\''],
+        tags: false,
+    },
+    Language {
+        names: &["css", "scss"],
+        keywords: "important",
+        ignore_case: false,
+        line_comments: &[],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '\''],
+        tags: false,
+    },
+    Language {
+        names: &["xml", "html", "svg"],
+        keywords: "",
+        ignore_case: false,
+        line_comments: &[],
+        block_comment: Some(("<!--", "-->")),
+        // Apostrophes are common in the text between tags
+        quotes: &['"'],
+        tags: true,
+    },
+    Language {
+        names: &["java", "kotlin"],
+        keywords: JAVA_KEYWORDS,
+        ignore_case: false,
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '\''],
+        tags: false,
+    },
+    Language {
+        names: &["javascript", "js", "typescript", "ts", "json"],
+        keywords: JS_KEYWORDS,
+        ignore_case: false,
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '\'', '`'],
+        tags: false,
+    },
+    Language {
+        names: &["php"],
+        keywords: PHP_KEYWORDS,
+        ignore_case: false,
+        line_comments: &["//", "#"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '\''],
+        tags: false,
+    },
+    Language {
+        names: &["python", "py"],
+        keywords: PYTHON_KEYWORDS,
+        ignore_case: false,
+        line_comments: &["#"],
+        block_comment: None,
+        quotes: &['"', '\''],
+        tags: false,
+    },
+    Language {
+        names: &["ruby", "rb"],
+        keywords: RUBY_KEYWORDS,
+        ignore_case: false,
+        line_comments: &["#"],
+        block_comment: None,
+        quotes: &['"', '\''],
+        tags: false,
+    },
+    Language {
+        names: &["rust", "rs"],
+        keywords: RUST_KEYWORDS,
+        ignore_case: false,
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"'],
+        tags: false,
+    },
+    Language {
+        names: &["go", "golang"],
+        keywords: GO_KEYWORDS,
+        ignore_case: false,
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '\'', '`'],
+        tags: false,
+    },
+    Language {
+        names: &["sql"],
+        keywords: SQL_KEYWORDS,
+        ignore_case: true,
+        line_comments: &["--"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '\''],
+        tags: false,
+    },
+];
+
+/// Colour the code lines of a block written in the given language.
+/// Lines of unknown languages are returned unchanged.
+pub(crate) fn highlight(
+    lines: Vec<Vec<Run>>,
+    language: &str,
+    colors: &SyntaxColors,
+) -> Vec<Vec<Run>> {
+    let text = lines
+        .iter()
+        .map(|runs| runs.iter().map(|run| run.text.as_str()).collect::<String>())
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let language = language.to_ascii_lowercase();
+    let tokens = if language == "diff" {
+        diff_tokens(&text)
+    } else {
+        match LANGUAGES
+            .iter()
+            .find(|lang| lang.names.contains(&language.as_str()))
+        {
+            Some(language) => tokens(&text, language),
+            None => return lines,
+        }
+    };
+
+    let mut line_start = 0;
+    lines
+        .into_iter()
+        .map(|runs| {
+            let length: usize = runs.iter().map(|run| run.text.len()).sum();
+            let line = line_start..line_start + length;
+            line_start = line.end + 1;
+            color_runs(runs, line, &tokens, colors)
+        })
+        .collect()
+}
+
+/// Find the tokens of a text, text without a token is left out
+fn tokens(text: &str, language: &Language) -> Vec<(Range<usize>, Token)> {
+    let mut tokens = Vec::new();
+    let mut index = 0;
+    // Whether the previous character was `<` or `</` in a language with tags
+    let mut after_tag_open = false;
+
+    while let Some(c) = text[index..].chars().next() {
+        let rest = &text[index..];
+        let start = index;
+
+        if let Some((open, close)) = language
+            .block_comment
+            .filter(|(open, _)| rest.starts_with(open))
+        {
+            let end = rest[open.len()..]
+                .find(close)
+                .map_or(text.len(), |end| index + open.len() + end + close.len());
+            tokens.push((start..end, Token::Comment));
+            index = end;
+        } else if language
+            .line_comments
+            .iter()
+            .any(|comment| rest.starts_with(comment))
+        {
+            index += rest.find('\n').unwrap_or(rest.len());
+            tokens.push((start..index, Token::Comment));
+        } else if language.quotes.contains(&c) {
+            index += string_length(rest, c);
+            tokens.push((start..index, Token::String));
+        } else if c.is_ascii_digit() {
+            index += word_length(rest);
+            tokens.push((start..index, Token::Number));
+        } else if c.is_alphabetic() || c == '_' {
+            index += word_length(rest);
+            let word = &text[start..index];
+            let keyword = language.keywords.split_whitespace().any(|keyword| {
+                keyword == word || (language.ignore_case && keyword.eq_ignore_ascii_case(word))
+            });
+            if keyword || after_tag_open {
+                tokens.push((start..index, Token::Keyword));
+            }
+        } else {
+            index += c.len_utf8();
+        }
+
+        after_tag_open =
+            language.tags && (text[..index].ends_with('<') || text[..index].ends_with("</"));
+    }
+    tokens
+}
+
+/// Length of a string literal starting with its quote, backslashes escape the next character
+fn string_length(text: &str, quote: char) -> usize {
+    let mut escaped = false;
+    for (index, c) in text.char_indices().skip(1) {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == quote {
+            return index + c.len_utf8();
+        } else if c == '\n' && quote != '`' {
+            // Only template strings span lines, others end at the line if not closed
+            return index;
+        }
+    }
+    text.len()
+}
+
+/// Length of an identifier or number at the start of the text
+fn word_length(text: &str) -> usize {
+    // Numbers may have a decimal point, identifiers end at a dot
+    let number = text.starts_with(|c: char| c.is_ascii_digit());
+    text.char_indices()
+        .find(|&(_, c)| !(c.is_alphanumeric() || c == '_' || (number && c == '.')))
+        .map_or(text.len(), |(index, _)| index)
+}
+
+/// Diffs are coloured by line, added and removed lines are marked by their first character
+fn diff_tokens(text: &str) -> Vec<(Range<usize>, Token)> {
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    for line in text.split('\n') {
+        let token = match line.chars().next() {
+            Some('+') => Some(Token::Addition),
+            Some('-') => Some(Token::Deletion),
+            Some('@') => Some(Token::Keyword),
+            _ => None,
+        };
+        if let Some(token) = token {
+            tokens.push((start..start + line.len(), token));
+        }
+        start += line.len() + 1;
+    }
+    tokens
+}
+
+/// Split the runs of a line at the tokens and colour them.
+/// `line` is the range of the line in the text of the whole block.
+fn color_runs(
+    runs: Vec<Run>,
+    line: Range<usize>,
+    tokens: &[(Range<usize>, Token)],
+    colors: &SyntaxColors,
+) -> Vec<Run> {
+    let tokens: Vec<_> = tokens
+        .iter()
+        .filter(|(range, _)| range.start < line.end && range.end > line.start)
+        .collect();
+
+    let mut colored = Vec::new();
+    let mut run_start = line.start;
+    for run in runs {
+        let run_range = run_start..run_start + run.text.len();
+        run_start = run_range.end;
+        let mut position = run_range.start;
+
+        for (range, token) in &tokens {
+            let start = range.start.max(run_range.start);
+            let end = range.end.min(run_range.end);
+            if start >= end {
+                continue;
+            }
+            if position < start {
+                colored.push(split_run(
+                    &run,
+                    position - run_range.start..start - run_range.start,
+                    None,
+                ));
+            }
+            let color = token_color(*token, colors);
+            colored.push(split_run(
+                &run,
+                start - run_range.start..end - run_range.start,
+                Some(color),
+            ));
+            position = end;
+        }
+        if position < run_range.end {
+            colored.push(split_run(
+                &run,
+                position - run_range.start..run.text.len(),
+                None,
+            ));
+        }
+    }
+    colored
+}
+
+/// A part of a run, text that has a colour of its own keeps it
+fn split_run(run: &Run, range: Range<usize>, color: Option<Color>) -> Run {
+    let mut part = run.clone();
+    part.text = run.text[range].to_string();
+    if let (Some(color), None) = (color, run.style.color()) {
+        part.style.set_color(color);
+    }
+    part
+}
+
+fn token_color(token: Token, colors: &SyntaxColors) -> Color {
+    match token {
+        Token::Keyword => colors.keyword,
+        Token::String => colors.string,
+        Token::Number => colors.number,
+        Token::Comment => colors.comment,
+        Token::Addition => colors.addition,
+        Token::Deletion => colors.deletion,
+    }
+}
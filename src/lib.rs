@@ -2,16 +2,28 @@
 //!
 //! Calling `DeltaPdf::new()` will parse the data according to the
 //! [Quill Delta specification](https://quilljs.com/docs/delta/) and return an error if the delta
-//! is invalid or has unsupported attributes.
+//! is invalid or has unsupported attributes. All formats built into Quill are parsed.
+//! `DeltaPdf::new_lenient()` keeps unknown attributes and embeds, for example from Quill plugins,
+//! and reports them as warnings instead.
 //!
-//! The following attributes are supported:
+//! The following attributes are rendered:
 //! - bold
 //! - italic
-//! - header
-//! - list
+//! - underline
+//! - strike
+//! - color and background (`#rrggbb`, `#rgb`, `rgb()` and CSS colour names)
+//! - link
+//! - code (in the font set with `DeltaPdf::set_code_font()`)
+//! - code-block (in the same font, highlighted by its language)
+//! - header (levels 1 to 6)
+//! - blockquote
+//! - align (left, center, right and justify)
+//! - list (nested with indent, checklists with drawn checkboxes)
 //! - image
 //!
-//! Only inserts are rendered. Deletes and retains are parsed but ignored.
+//! Only inserts are rendered. Deletes and retains are parsed but ignored, and so are
+//! video and formula embeds.
+//! `DeltaPdf::write_to_pdf()` returns a [`RenderReport`] listing everything that was left out.
 //!
 //! ## Example Usage
 //!
@@ -31,74 +43,237 @@
 //! }
 //! ```
 //!
+//! genpdf can not create clickable links. Use `DeltaPdf::render()` or
+//! `DeltaPdf::render_to_file()` instead of rendering the document yourself
+//! to turn links into PDF link annotations. The page margins are then taken from the theme.
+//!
+//! Spacing, heading sizes, list markers and other visual choices can be changed by
+//! setting a [`Theme`] with `DeltaPdf::set_theme()`.
+//!
 //! This library makes use of genpdf. If you want to customize the look of the PDF file feel free
 //! to take a look at their [documentation](https://docs.rs/genpdf/latest/genpdf/index.html)
 
+mod code;
+mod color;
 pub mod delta;
+mod highlight;
+mod links;
+mod list;
+mod paragraph;
+mod quote;
+pub mod report;
+pub mod theme;
 
-use std::path::PathBuf;
+pub use report::RenderReport;
+pub use theme::{HeadingStyle, Spacing, Theme};
 
-use delta::{Attribute, Change, Delta, DeltaType, ListType};
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use code::CodeBlock;
+use delta::{
+    AlignType, Attribute, Change, CodeBlockType, Delta, DeltaType, ListType, ParseWarning,
+};
 use genpdf::{
-    elements::{Image, Paragraph},
-    style::{Style, StyledString},
-    Document, Element, Margins,
+    elements::{Image, LinearLayout, PaddedElement},
+    error::Error,
+    fonts::{FontData, FontFamily},
+    render::Area,
+    style::Style,
+    Context, Document, Element, Margins, Mm, RenderResult,
 };
+use links::{LinkDecorator, Links, SharedLinks};
+use list::{ListItem, ListNumbers, Marker};
+use paragraph::{Alignment, RichParagraph, Run};
+use quote::Quote;
+use report::Dropped;
+use url::Url;
 
 #[derive(Debug)]
-/// Error type for DeltaPdf
+/// Error type for DeltaPdf.
+/// Errors caused by a single op carry the index of the op in the Delta.
 pub enum DeltaPdfError {
-    ImageUrlError,
-    ImagePathNotSet,
+    /// The JSON is invalid or does not follow the Delta format
+    ParseError(serde_json::Error),
+    /// The Delta has an attribute or embed that Quill does not define
+    Unsupported(ParseWarning),
+    /// No image name could be taken from the URL of an image
+    ImageUrlError {
+        op: usize,
+        url: Url,
+    },
+    /// The Delta has an image but the image directory is not set
+    ImagePathNotSet {
+        op: usize,
+        url: Url,
+    },
+    /// An image could not be loaded from the image directory
+    ImageError {
+        op: usize,
+        path: PathBuf,
+        source: genpdf::error::Error,
+    },
     PdfError(genpdf::error::Error),
+    /// The links could not be added to the rendered PDF
+    LinkError(lopdf::Error),
+    IoError(std::io::Error),
 }
 
-impl std::error::Error for DeltaPdfError {}
+impl std::error::Error for DeltaPdfError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DeltaPdfError::ParseError(e) => Some(e),
+            DeltaPdfError::ImageError { source, .. } => Some(source),
+            DeltaPdfError::PdfError(e) => Some(e),
+            DeltaPdfError::LinkError(e) => Some(e),
+            DeltaPdfError::IoError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
 
 impl std::fmt::Display for DeltaPdfError {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
-            DeltaPdfError::ImageUrlError => write!(f, "The image url could not be parsed"),
-            DeltaPdfError::ImagePathNotSet => write!(
+            DeltaPdfError::ParseError(e) => write!(f, "The Delta could not be parsed: {}", e),
+            DeltaPdfError::Unsupported(warning) => {
+                write!(f, "The Delta is not supported: {}", warning)
+            }
+            DeltaPdfError::ImageUrlError { op, url } => {
+                write!(f, "op {}: the image url {} could not be parsed", op, url)
+            }
+            DeltaPdfError::ImagePathNotSet { op, url } => write!(
+                f,
+                "op {}: parsed Delta had the image {} but the image directory is not set",
+                op, url
+            ),
+            DeltaPdfError::ImageError { op, path, source } => write!(
                 f,
-                "Parsed Delta had an image but the image directory is not set."
+                "op {}: the image {} could not be loaded: {}",
+                op,
+                path.display(),
+                source
             ),
             DeltaPdfError::PdfError(e) => write!(f, "{}", e),
+            DeltaPdfError::LinkError(e) => {
+                write!(f, "The links could not be added to the PDF: {}", e)
+            }
+            DeltaPdfError::IoError(e) => write!(f, "{}", e),
         }
     }
 }
 
+impl From<serde_json::Error> for DeltaPdfError {
+    fn from(err: serde_json::Error) -> Self {
+        DeltaPdfError::ParseError(err)
+    }
+}
+
 impl From<genpdf::error::Error> for DeltaPdfError {
     fn from(err: genpdf::error::Error) -> Self {
         DeltaPdfError::PdfError(err)
     }
 }
 
+impl From<std::io::Error> for DeltaPdfError {
+    fn from(err: std::io::Error) -> Self {
+        DeltaPdfError::IoError(err)
+    }
+}
+
 impl From<Delta> for DeltaPdf {
     fn from(delta: Delta) -> Self {
         Self {
             delta,
             images_path: None,
+            theme: Theme::default(),
+            code_font: None,
         }
     }
 }
 
 enum PdfElement {
-    String(StyledString),
+    /// A line of text made up of differently styled runs
+    Paragraph {
+        runs: Vec<Run>,
+        padding: Spacing,
+        /// Space in front of nested list items, added to the left padding
+        indent: Mm,
+        alignment: Alignment,
+        /// Marker of a list item and its style, placed in a column before the text
+        marker: Option<(Marker, Style)>,
+    },
     Image(Image),
+    /// Consecutive code lines of the same language, each made up of runs
+    CodeBlock {
+        language: Option<String>,
+        lines: Vec<Vec<Run>>,
+    },
+    /// Elements of consecutive blockquote lines
+    Quote(Vec<PdfElement>),
+}
+
+/// State shared by the ops of a Delta while it is rendered
+struct RenderState {
+    report: RenderReport,
+    /// Style of code blocks and inline code
+    code_style: Style,
+    list_numbers: ListNumbers,
+}
+
+/// The genpdf element created for a `PdfElement`.
+/// Documents and layouts only take elements of a known type, so every kind is a variant.
+enum Block {
+    Paragraph(PaddedElement<RichParagraph>),
+    ListItem(PaddedElement<ListItem>),
+    Image(PaddedElement<Image>),
+    Code(PaddedElement<CodeBlock>),
+    Quote(PaddedElement<Quote>),
+}
+
+impl Element for Block {
+    fn render(
+        &mut self,
+        context: &Context,
+        area: Area<'_>,
+        style: Style,
+    ) -> Result<RenderResult, Error> {
+        match self {
+            Block::Paragraph(paragraph) => paragraph.render(context, area, style),
+            Block::ListItem(item) => item.render(context, area, style),
+            Block::Image(image) => image.render(context, area, style),
+            Block::Code(code) => code.render(context, area, style),
+            Block::Quote(quote) => quote.render(context, area, style),
+        }
+    }
 }
 
 /// Struct that holds the parsed Delta.
 pub struct DeltaPdf {
     pub delta: Delta,
     images_path: Option<PathBuf>,
+    theme: Theme,
+    code_font: Option<FontFamily<FontData>>,
 }
 
 impl DeltaPdf {
     /// Parse a Quill Delta.
-    pub fn new(delta: String) -> serde_json::Result<DeltaPdf> {
-        let delta_serialized: Delta = delta.parse()?;
-        Ok(delta_serialized.into())
+    /// Attributes and embeds that Quill does not define are an error.
+    pub fn new(delta: String) -> Result<DeltaPdf, DeltaPdfError> {
+        let delta = Delta::parse_lenient(&delta)?;
+        if let Some(warning) = delta.warnings().into_iter().next() {
+            return Err(DeltaPdfError::Unsupported(warning));
+        }
+        Ok(delta.into())
+    }
+
+    /// Parse a Quill Delta keeping attributes and embeds that Quill does not define.
+    /// Unknown attributes are ignored and unknown embeds are skipped when rendering,
+    /// `delta.warnings()` lists them.
+    pub fn new_lenient(delta: String) -> Result<DeltaPdf, DeltaPdfError> {
+        Ok(Delta::parse_lenient(&delta)?.into())
     }
 
     /// Set the location of where images are located.
@@ -111,129 +286,526 @@ impl DeltaPdf {
 
     /// Convert the parsed Delta to a string.
     /// This will ignore formatting and images.
+    #[allow(clippy::inherent_to_string)]
     pub fn to_string(&self) -> String {
         let mut result = String::new();
         for op in &self.delta.ops {
             if let Change::Insert(DeltaType::String(text)) = &op.change {
-                result.push_str(&text);
+                result.push_str(text);
             }
         }
         result
     }
 
-    /// Set the heading font size for the previous string
-    fn set_heading(strings: &mut [PdfElement], font_size: u8) {
-        if let Some(PdfElement::String(last)) = strings.last_mut() {
-            last.style.set_font_size(font_size);
+    /// Set the monospace font family used for code.
+    /// Code uses the default font of the document if it is not set.
+    pub fn set_code_font(&mut self, font_family: FontFamily<FontData>) {
+        self.code_font = Some(font_family);
+    }
+
+    /// Set the theme used to render the Delta
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+    }
+
+    /// Get the theme used to render the Delta
+    pub fn theme(&self) -> &Theme {
+        &self.theme
+    }
+
+    /// Get the mutable theme used to render the Delta
+    pub fn theme_mut(&mut self) -> &mut Theme {
+        &mut self.theme
+    }
+
+    /// Set the style of a header level between 1 and 6.
+    /// Other levels are ignored.
+    pub fn set_heading_style(&mut self, level: u8, style: HeadingStyle) {
+        if let Some(heading) = self.theme.heading_mut(level) {
+            *heading = style;
+        }
+    }
+
+    /// Set the font sizes of header levels 1 to 6
+    pub fn set_heading_sizes(&mut self, sizes: [u8; 6]) {
+        for (heading, size) in self.theme.headings.iter_mut().zip(sizes) {
+            heading.font_size = size;
         }
     }
 
-    // Sets the prefix for the previous string
-    fn set_prefix(strings: &mut [PdfElement], prefix: &str) {
-        if let Some(PdfElement::String(last)) = strings.last_mut() {
-            last.s.insert_str(0, prefix);
+    /// Apply the heading style to the paragraphs of a line
+    fn set_heading(elements: &mut [PdfElement], heading: &HeadingStyle) {
+        for element in elements {
+            if let PdfElement::Paragraph { runs, padding, .. } = element {
+                for run in runs {
+                    run.style.set_font_size(heading.font_size);
+                    if heading.bold {
+                        run.style.set_bold();
+                    }
+                }
+                *padding = heading.spacing;
+            }
+        }
+    }
+
+    /// Align the paragraphs and images of a line
+    fn set_alignment(elements: &mut [PdfElement], align: &AlignType) {
+        for element in elements {
+            match (element, align) {
+                (PdfElement::Paragraph { alignment, .. }, _) => {
+                    *alignment = match align {
+                        AlignType::Center => Alignment::Center,
+                        AlignType::Right => Alignment::Right,
+                        AlignType::Justify => Alignment::Justify,
+                    }
+                }
+                (PdfElement::Image(image), AlignType::Center) => {
+                    image.set_alignment(genpdf::Alignment::Center)
+                }
+                (PdfElement::Image(image), AlignType::Right) => {
+                    image.set_alignment(genpdf::Alignment::Right)
+                }
+                _ => (),
+            }
+        }
+    }
+
+    /// Indent the paragraphs of a line
+    fn set_indent(elements: &mut [PdfElement], indent: Mm) {
+        for element in elements {
+            if let PdfElement::Paragraph {
+                indent: paragraph_indent,
+                ..
+            } = element
+            {
+                *paragraph_indent = indent;
+            }
+        }
+    }
+
+    /// Strike through the text of a line
+    fn set_strike(elements: &mut [PdfElement]) {
+        for element in elements {
+            if let PdfElement::Paragraph { runs, .. } = element {
+                runs.iter_mut().for_each(|run| run.strike = true);
+            }
+        }
+    }
+
+    /// Set the list marker of the first paragraph of a line.
+    /// The marker follows the heading of the line, but none of the inline formats of its text.
+    fn set_marker(
+        elements: &mut [PdfElement],
+        list_marker: Marker,
+        heading: Option<&HeadingStyle>,
+    ) {
+        let mut style = Style::new();
+        if let Some(heading) = heading {
+            style.set_font_size(heading.font_size);
+            if heading.bold {
+                style.set_bold();
+            }
+        }
+        let first = elements.iter_mut().find_map(|element| match element {
+            PdfElement::Paragraph { marker, .. } => Some(marker),
+            PdfElement::Image(_) | PdfElement::CodeBlock { .. } | PdfElement::Quote(_) => None,
+        });
+        if let Some(marker) = first {
+            *marker = Some((list_marker, style));
         }
     }
 
-    /// Write the parsed Delta to a PDF document
-    pub fn write_to_pdf(&self, document: &mut Document) -> Result<(), DeltaPdfError> {
+    /// Add the text of a line to the code block before it if it has the same language,
+    /// otherwise start a new code block
+    fn push_code_line(
+        elements: &mut Vec<PdfElement>,
+        line_elements: Vec<PdfElement>,
+        line_language: Option<&str>,
+    ) {
+        let runs = line_elements
+            .into_iter()
+            .flat_map(|element| match element {
+                PdfElement::Paragraph { runs, .. } => runs,
+                PdfElement::Image(_) | PdfElement::CodeBlock { .. } | PdfElement::Quote(_) => {
+                    Vec::new()
+                }
+            })
+            .collect();
+        match elements.last_mut() {
+            Some(PdfElement::CodeBlock { language, lines })
+                if language.as_deref() == line_language =>
+            {
+                lines.push(runs)
+            }
+            _ => elements.push(PdfElement::CodeBlock {
+                language: line_language.map(str::to_string),
+                lines: vec![runs],
+            }),
+        }
+    }
+
+    /// Add the elements of a blockquote line to the quote before it or start a new quote
+    fn push_quote_line(elements: &mut Vec<PdfElement>, line_elements: Vec<PdfElement>) {
+        match elements.last_mut() {
+            Some(PdfElement::Quote(quote)) => quote.extend(line_elements),
+            _ => elements.push(PdfElement::Quote(line_elements)),
+        }
+    }
+
+    /// Style of code on top of the document style.
+    /// The code font is added to the document if it is set.
+    fn code_style(&self, document: &mut Document) -> Style {
+        let code = &self.theme.code;
+        let mut style = Style::new();
+        if let Some(font_family) = &self.code_font {
+            style.set_font_family(document.add_font_family(font_family.clone()));
+        }
+        if let Some(font_size) = code.font_size {
+            style.set_font_size(font_size);
+        }
+        if let Some(color) = code.color {
+            style.set_color(color);
+        }
+        style
+    }
+
+    /// Create a regular paragraph from styled runs
+    fn paragraph(&self, runs: Vec<Run>) -> PdfElement {
+        PdfElement::Paragraph {
+            runs,
+            padding: self.theme.paragraph_spacing,
+            indent: Mm::from(0),
+            alignment: Alignment::Left,
+            marker: None,
+        }
+    }
+
+    /// Create an inline run styled by the attributes of its op
+    fn inline_run(
+        &self,
+        text: &str,
+        attributes: &Option<Vec<Attribute>>,
+        op: usize,
+        state: &mut RenderState,
+    ) -> Run {
+        let report = &mut state.report;
+        let mut run = Run::new(text, Style::new());
+        let mut code = false;
+        for attribute in attributes.iter().flatten() {
+            match attribute {
+                Attribute::Bold(bold) => {
+                    if *bold {
+                        run.style.set_bold();
+                    }
+                }
+                Attribute::Italic(italic) => {
+                    if *italic {
+                        run.style.set_italic();
+                    }
+                }
+                Attribute::Underline(underline) => run.underline = *underline,
+                Attribute::Strike(strike) => run.strike = *strike,
+                Attribute::Code(inline_code) => code = *inline_code,
+                Attribute::Color(color) => match color::parse_color(color) {
+                    Some(color) => run.style.set_color(color),
+                    None => report.drop_attribute(op, attribute),
+                },
+                Attribute::Background(color) => match color::parse_color(color) {
+                    Some(color) => run.background = Some(color),
+                    None => report.drop_attribute(op, attribute),
+                },
+                Attribute::Link(url) => run.link = Some(url.clone()),
+                _ => report.drop_attribute(op, attribute),
+            }
+        }
+
+        if run.link.is_some() {
+            let link = &self.theme.link;
+            // An explicit colour of the text wins over the colour of links
+            if let (Some(color), None) = (link.color, run.style.color()) {
+                run.style.set_color(color);
+            }
+            run.underline |= link.underline;
+        }
+        if code {
+            // Bold, italic and explicit colours still apply to the code
+            run.style = state.code_style.and(run.style);
+            if run.background.is_none() {
+                run.background = self.theme.code.background;
+            }
+        }
+        run
+    }
+
+    /// Load an image from the image directory
+    fn load_image(&self, image: &delta::Image, op: usize) -> Result<Image, DeltaPdfError> {
+        let url = &image.image;
+        let image_name = url
+            .path_segments()
+            .and_then(|mut segments| segments.next_back())
+            .ok_or_else(|| DeltaPdfError::ImageUrlError {
+                op,
+                url: url.clone(),
+            })?;
+        let full_path = self
+            .images_path
+            .as_ref()
+            .ok_or_else(|| DeltaPdfError::ImagePathNotSet {
+                op,
+                url: url.clone(),
+            })?
+            .join(image_name);
+        Image::from_path(&full_path).map_err(|source| DeltaPdfError::ImageError {
+            op,
+            path: full_path,
+            source,
+        })
+    }
+
+    /// Write the parsed Delta to a PDF document.
+    /// The returned report lists the ops and attributes that could not be rendered.
+    ///
+    /// Links are styled but not clickable, see [`DeltaPdf::render`].
+    pub fn write_to_pdf(&self, document: &mut Document) -> Result<RenderReport, DeltaPdfError> {
+        self.write_elements(document, None)
+    }
+
+    /// Write the parsed Delta to the document and render it as a PDF with clickable links.
+    /// The page decorator of the document is replaced by one with the page margins of the theme.
+    pub fn render(
+        &self,
+        mut document: Document,
+        mut writer: impl Write,
+    ) -> Result<RenderReport, DeltaPdfError> {
+        let links = Links::new(self.theme.page_margins);
+        document.set_page_decorator(LinkDecorator(links.clone()));
+        let report = self.write_elements(&mut document, Some(&links))?;
+        let mut pdf = Vec::new();
+        document.render(&mut pdf)?;
+        let pdf =
+            links::add_link_annotations(&pdf, &links.borrow()).map_err(DeltaPdfError::LinkError)?;
+        writer.write_all(&pdf)?;
+        Ok(report)
+    }
+
+    /// Write the parsed Delta to the document and render it to a PDF file with clickable links
+    pub fn render_to_file(
+        &self,
+        document: Document,
+        path: impl AsRef<Path>,
+    ) -> Result<RenderReport, DeltaPdfError> {
+        let file = std::fs::File::create(path)?;
+        self.render(document, std::io::BufWriter::new(file))
+    }
+
+    /// Push the elements of the Delta to the document.
+    /// The areas of links are recorded in `links` while the document is rendered.
+    fn write_elements(
+        &self,
+        document: &mut Document,
+        links: Option<&SharedLinks>,
+    ) -> Result<RenderReport, DeltaPdfError> {
+        let mut state = RenderState {
+            report: RenderReport::default(),
+            code_style: self.code_style(document),
+            list_numbers: ListNumbers::default(),
+        };
         let mut pdf_elements: Vec<PdfElement> = Vec::new();
 
-        let mut ordered_list_index: u32 = 1;
+        for (index, op) in self.delta.ops.iter().enumerate() {
+            if !matches!(op.change, Change::Insert(_)) {
+                state.report.push(index, Dropped::Change);
+            }
+        }
 
-        for op in &self.delta.ops {
-            let delta_type = match &op.change {
-                Change::Insert(x) | Change::Delete(x) | Change::Retain(x) => x,
-            };
+        for line in self.delta.lines() {
+            let mut line_elements: Vec<PdfElement> = Vec::new();
+            let mut runs: Vec<Run> = Vec::new();
+            let code_block =
+                line.attributes
+                    .iter()
+                    .flatten()
+                    .find_map(|attribute| match attribute {
+                        Attribute::CodeBlock(CodeBlockType::Plain(false)) => None,
+                        Attribute::CodeBlock(code) => Some(code.language()),
+                        _ => None,
+                    });
 
-            match delta_type {
-                DeltaType::String(text) => {
-                    let mut style = Style::new();
-
-                    if let Some(attributes) = &op.attributes {
-                        for attribute in attributes {
-                            match attribute {
-                                Attribute::Bold(true) => style.set_bold(),
-                                Attribute::Italic(true) => style.set_italic(),
-                                Attribute::Header(1) => Self::set_heading(&mut pdf_elements, 18),
-                                Attribute::Header(2) => Self::set_heading(&mut pdf_elements, 16),
-                                Attribute::List(list_type) => {
-                                    match list_type {
-                                        ListType::Bullet => {
-                                            Self::set_prefix(&mut pdf_elements, "      • ")
-                                        }
-                                        ListType::Ordered => {
-                                            let mut elem_iter = pdf_elements.iter().rev().fuse();
-                                            let _current = elem_iter.next();
-
-                                            // Reset the index if the previous line does not
-                                            // contain the previous index prefix
-                                            if let Some(PdfElement::String(last)) = elem_iter.next()
-                                            {
-                                                if !last.s.contains(&format!(
-                                                    "{}. ",
-                                                    ordered_list_index.saturating_sub(1)
-                                                )) {
-                                                    ordered_list_index = 1;
-                                                }
-                                            }
-
-                                            Self::set_prefix(
-                                                &mut pdf_elements,
-                                                &format!("      {}. ", ordered_list_index),
-                                            );
-                                            ordered_list_index += 1;
-                                        }
-                                    }
-                                }
-                                _ => (),
-                            }
+            for (index, op) in &line.ops {
+                match &op.change {
+                    Change::Insert(DeltaType::String(text)) => {
+                        runs.push(self.inline_run(text, &op.attributes, *index, &mut state));
+                    }
+                    // Code blocks only hold text, so their images are not even loaded
+                    Change::Insert(DeltaType::Image(_)) if code_block.is_some() => {
+                        state.report.push(*index, Dropped::Embed("image".into()));
+                    }
+                    Change::Insert(DeltaType::Image(image)) => {
+                        if !runs.is_empty() {
+                            line_elements.push(self.paragraph(std::mem::take(&mut runs)));
+                        }
+                        line_elements.push(PdfElement::Image(self.load_image(image, *index)?));
+                        // Images keep their own size, their formats are not applied
+                        for attribute in op.attributes.iter().flatten() {
+                            state.report.drop_attribute(*index, attribute);
                         }
                     }
+                    // Videos and formulas have nothing that can be printed
+                    Change::Insert(DeltaType::Video(_)) => {
+                        state.report.push(*index, Dropped::Embed("video".into()));
+                    }
+                    Change::Insert(DeltaType::Formula(_)) => {
+                        state.report.push(*index, Dropped::Embed("formula".into()));
+                    }
+                    // Unknown embeds kept by lenient parsing have nothing to render
+                    Change::Insert(DeltaType::Other(embed)) => {
+                        let embed = embed.keys().next().cloned().unwrap_or_default();
+                        state.report.push(*index, Dropped::Embed(embed));
+                    }
+                    Change::Delete(_) | Change::Retain(_) => (),
+                }
+            }
 
-                    let strings = text.split('\n');
+            if !runs.is_empty() || line_elements.is_empty() {
+                line_elements.push(self.paragraph(runs));
+            }
 
-                    for (i, string) in strings.enumerate() {
-                        // Always append the first string to the last string to handle lines correctly
-                        if i == 0 {
-                            if let Some(PdfElement::String(last)) = pdf_elements.last_mut() {
-                                last.s.push_str(string);
-                                continue;
-                            }
+            let newline = line.index.unwrap_or_default();
+            let mut quote = false;
+            let mut heading = None;
+            let mut list = None;
+            let mut indent = None;
+            for attribute in line.attributes.iter().flatten() {
+                match attribute {
+                    Attribute::CodeBlock(_) => (),
+                    Attribute::Blockquote(blockquote) => quote = *blockquote,
+                    // Code keeps its own alignment
+                    Attribute::Align(_) if code_block.is_some() => {
+                        state.report.drop_attribute(newline, attribute)
+                    }
+                    Attribute::Align(align) => Self::set_alignment(&mut line_elements, align),
+                    Attribute::Header(level) => match self.theme.heading(*level) {
+                        Some(style) => {
+                            Self::set_heading(&mut line_elements, style);
+                            heading = Some(style);
                         }
+                        None => state.report.drop_attribute(newline, attribute),
+                    },
+                    Attribute::List(list_type) => list = Some(list_type),
+                    Attribute::Indent(level) => indent = Some((*level, attribute)),
+                    // The newline itself is not printed, inline formats are reported
+                    // with the text of the op if they could not be rendered
+                    _ if attribute.is_inline() => (),
+                    _ => state.report.drop_attribute(newline, attribute),
+                }
+            }
 
-                        let styled = StyledString::new(string, style);
-                        pdf_elements.push(PdfElement::String(styled));
-                    }
+            // The indent of a list item is its nesting level
+            let level = match (list, indent) {
+                (Some(_), Some((level, _))) => level,
+                (None, Some((_, attribute))) => {
+                    state.report.drop_attribute(newline, attribute);
+                    0
                 }
-                DeltaType::Image(image) => {
-                    let image_name = image
-                        .image
-                        .path_segments()
-                        .ok_or(DeltaPdfError::ImageUrlError)?
-                        .last()
-                        .ok_or(DeltaPdfError::ImageUrlError)?;
-                    let full_path = self
-                        .images_path
-                        .as_ref()
-                        .ok_or(DeltaPdfError::ImagePathNotSet)?
-                        .join(image_name);
-                    let image = Image::from_path(full_path)?;
-                    pdf_elements.push(PdfElement::Image(image));
+                (_, None) => 0,
+            };
+            let list_style = &self.theme.list;
+            match list {
+                Some(list_type) => {
+                    let number = state.list_numbers.next(list_type, level);
+                    let marker = match list_type {
+                        ListType::Bullet => Marker::Text(list_style.bullet(level).to_string()),
+                        ListType::Ordered => Marker::Text(list_style.number(level, number)),
+                        ListType::Checked => Marker::Checkbox(true),
+                        ListType::Unchecked => Marker::Checkbox(false),
+                    };
+                    if *list_type == ListType::Checked && list_style.strike_checked {
+                        Self::set_strike(&mut line_elements);
+                    }
+                    Self::set_marker(&mut line_elements, marker, heading);
                 }
+                None => state.list_numbers.end(),
+            }
+            if list.is_some() {
+                Self::set_indent(&mut line_elements, list_style.indent * f64::from(level));
+            }
+
+            if let Some(language) = code_block {
+                Self::push_code_line(&mut pdf_elements, line_elements, language);
+            } else if quote {
+                Self::push_quote_line(&mut pdf_elements, line_elements);
+            } else {
+                pdf_elements.extend(line_elements);
             }
         }
 
         for element in pdf_elements {
-            match element {
-                PdfElement::String(string) => {
-                    document.push(Paragraph::new(string).padded(Margins::trbl(0, 0, 1, 0)));
+            document.push(self.element(element, &state, links, Mm::from(0)));
+        }
+        Ok(state.report)
+    }
+
+    /// Create the genpdf element that renders an element of the Delta.
+    /// `left` is the space between the page margin and the element, needed to place links.
+    fn element(
+        &self,
+        element: PdfElement,
+        state: &RenderState,
+        links: Option<&SharedLinks>,
+        left: Mm,
+    ) -> Block {
+        match element {
+            PdfElement::Paragraph {
+                runs,
+                padding,
+                indent,
+                alignment,
+                marker,
+            } => {
+                let left_padding = padding.left + indent;
+                let margins =
+                    Margins::trbl(padding.top, padding.right, padding.bottom, left_padding);
+                let paragraph = RichParagraph::new(runs).aligned(alignment);
+                match marker {
+                    Some(marker) => {
+                        let list = &self.theme.list;
+                        let paragraph =
+                            paragraph.with_links(links.cloned(), left + left_padding + list.indent);
+                        let item =
+                            ListItem::new(marker, paragraph, list.indent, list.marker_spacing);
+                        Block::ListItem(item.padded(margins))
+                    }
+                    None => Block::Paragraph(
+                        paragraph
+                            .with_links(links.cloned(), left + left_padding)
+                            .padded(margins),
+                    ),
+                }
+            }
+            PdfElement::Image(image) => Block::Image(image.padded(self.theme.image_spacing)),
+            PdfElement::CodeBlock {
+                language,
+                mut lines,
+            } => {
+                let code = &self.theme.code;
+                if let (Some(language), Some(colors)) = (language, &code.syntax) {
+                    lines = highlight::highlight(lines, &language, colors);
                 }
-                PdfElement::Image(image) => {
-                    document.push(image.padded(1));
+                let spacing = self.theme.paragraph_spacing;
+                let block = CodeBlock::new(lines, state.code_style, code.background, code.padding)
+                    .with_links(links.cloned(), left + spacing.left);
+                Block::Code(block.padded(spacing))
+            }
+            PdfElement::Quote(elements) => {
+                let quote = self.theme.quote;
+                let spacing = self.theme.paragraph_spacing;
+                let content_left = left + spacing.left + Quote::inset(quote);
+                let mut content = LinearLayout::vertical();
+                for element in elements {
+                    content.push(self.element(element, state, links, content_left));
                 }
+                Block::Quote(Quote::new(content, quote).padded(spacing))
             }
         }
-        Ok(())
     }
 }
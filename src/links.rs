@@ -0,0 +1,125 @@
+//! Clickable links in the rendered PDF.
+//!
+//! genpdf can not create annotations and does not tell where an area is placed on the page.
+//! `DeltaPdf::render` therefore sets a page decorator with known margins that counts the pages.
+//! Paragraphs record the rectangle of every linked piece of text relative to those margins while
+//! they are rendered, and the link annotations are added to the PDF afterwards.
+
+use std::{cell::RefCell, rc::Rc};
+
+use genpdf::{
+    error::Error, render::Area, style::Style, Context, Margins, Mm, PageDecorator, Position, Size,
+};
+use lopdf::{dictionary, Document, Object, StringFormat};
+
+use crate::{paragraph::points, theme::Spacing};
+
+/// A linked rectangle on a page
+#[derive(Debug)]
+struct LinkArea {
+    /// Number of the page starting at 1, like the keys of `Document::get_pages`
+    page: u32,
+    /// Left, bottom, right and top edge in points from the bottom left corner of the page
+    rect: [f64; 4],
+    url: String,
+}
+
+/// The links of a document that is being rendered
+#[derive(Debug, Default)]
+pub(crate) struct Links {
+    margins: Spacing,
+    /// Number of the page that is being rendered
+    page: u32,
+    areas: Vec<LinkArea>,
+}
+
+/// Links shared by the page decorator and the paragraphs of a document
+pub(crate) type SharedLinks = Rc<RefCell<Links>>;
+
+impl Links {
+    pub(crate) fn new(margins: Spacing) -> SharedLinks {
+        Rc::new(RefCell::new(Self {
+            margins,
+            ..Default::default()
+        }))
+    }
+
+    /// Record a linked rectangle of an area.
+    /// `left` is the space between the page margin and the area. The bottom of the area has
+    /// to be the bottom page margin, which holds for all areas nested in padded elements and
+    /// layouts as they only ever move the top and the sides.
+    pub(crate) fn add(
+        &mut self,
+        area: &Area<'_>,
+        left: Mm,
+        position: Position,
+        size: Size,
+        url: &str,
+    ) {
+        let left = points(self.margins.left + left + position.x);
+        let top = points(self.margins.bottom + area.size().height - position.y);
+        self.areas.push(LinkArea {
+            page: self.page,
+            rect: [
+                left,
+                top - points(size.height),
+                left + points(size.width),
+                top,
+            ],
+            url: url.to_string(),
+        });
+    }
+}
+
+/// Page decorator that applies the page margins and counts the rendered pages
+pub(crate) struct LinkDecorator(pub SharedLinks);
+
+impl PageDecorator for LinkDecorator {
+    fn decorate_page<'a>(
+        &mut self,
+        _context: &Context,
+        mut area: Area<'a>,
+        _style: Style,
+    ) -> Result<Area<'a>, Error> {
+        let mut links = self.0.borrow_mut();
+        links.page += 1;
+        area.add_margins(Margins::from(links.margins));
+        Ok(area)
+    }
+}
+
+/// Add annotations for the recorded links to a rendered PDF
+pub(crate) fn add_link_annotations(pdf: &[u8], links: &Links) -> lopdf::Result<Vec<u8>> {
+    if links.areas.is_empty() {
+        return Ok(pdf.to_vec());
+    }
+    let mut document = Document::load_mem(pdf)?;
+    let pages = document.get_pages();
+
+    for area in &links.areas {
+        let Some(&page_id) = pages.get(&area.page) else {
+            continue;
+        };
+        let annotation = document.add_object(dictionary! {
+            "Type" => "Annot",
+            "Subtype" => "Link",
+            "Rect" => area.rect.iter().map(|&value| Object::Real(value)).collect::<Vec<_>>(),
+            "Border" => vec![0.into(), 0.into(), 0.into()],
+            "A" => dictionary! {
+                "Type" => "Action",
+                "S" => "URI",
+                "URI" => Object::String(area.url.as_bytes().to_vec(), StringFormat::Literal),
+            },
+        });
+
+        let page = document.get_object_mut(page_id)?.as_dict_mut()?;
+        match page.get_mut(b"Annots").and_then(Object::as_array_mut) {
+            Ok(existing) => existing.push(Object::Reference(annotation)),
+            Err(_) => page.set("Annots", vec![Object::Reference(annotation)]),
+        }
+    }
+
+    let mut pdf = Vec::new();
+    document.save_to(&mut pdf)?;
+    Ok(pdf)
+}
@@ -0,0 +1,194 @@
+//! Numbering and layout of Quill lists.
+
+use genpdf::{
+    error::Error,
+    render::Area,
+    style::{Color, Style},
+    Context, Element, Margins, Mm, Position, RenderResult,
+};
+
+use crate::{
+    delta::ListType,
+    paragraph::{glyph_height, RichParagraph},
+};
+
+/// Numbers of the items of the lists being rendered, by nesting level.
+/// Quill has no list container, a list is the run of consecutive list lines.
+#[derive(Debug, Default)]
+pub(crate) struct ListNumbers {
+    /// Type and number of the last item of every level up to the current one
+    levels: Vec<Option<(ListType, u32)>>,
+}
+
+impl ListNumbers {
+    /// Number the next list item at a nesting level.
+    /// A new list starts when the type of the list at the level changes.
+    pub(crate) fn next(&mut self, list: &ListType, level: u8) -> u32 {
+        let level = usize::from(level);
+        // The lists nested in the previous item end with it
+        self.levels.resize(level + 1, None);
+        let number = match &self.levels[level] {
+            Some((previous, number)) if previous == list => number + 1,
+            _ => 1,
+        };
+        self.levels[level] = Some((list.clone(), number));
+        number
+    }
+
+    /// End all lists, called for every line that is not a list item
+    pub(crate) fn end(&mut self) {
+        self.levels.clear();
+    }
+}
+
+/// What is placed in front of a list item
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Marker {
+    /// A bullet or the number of the item
+    Text(String),
+    /// A drawn box that is ticked if the item is checked
+    Checkbox(bool),
+}
+
+/// A list item laid out as a marker column and a text column,
+/// so wrapped lines of the item start under its text rather than under its marker.
+pub(crate) struct ListItem {
+    marker: Marker,
+    /// Style of the marker, it is not affected by the formats of the text
+    marker_style: Style,
+    text: RichParagraph,
+    /// Width of the marker column
+    marker_width: Mm,
+    /// Space between the marker and the text
+    spacing: Mm,
+    /// Whether the marker was rendered, items spanning pages only have it on the first one
+    marker_done: bool,
+}
+
+impl ListItem {
+    pub(crate) fn new(
+        (marker, marker_style): (Marker, Style),
+        text: RichParagraph,
+        marker_width: Mm,
+        spacing: Mm,
+    ) -> Self {
+        Self {
+            marker,
+            marker_style,
+            text,
+            marker_width,
+            spacing,
+            marker_done: false,
+        }
+    }
+}
+
+impl Element for ListItem {
+    fn render(
+        &mut self,
+        context: &Context,
+        area: Area<'_>,
+        style: Style,
+    ) -> Result<RenderResult, Error> {
+        let mut text_area = area.clone();
+        text_area.add_margins(Margins::trbl(0, 0, 0, self.marker_width));
+        let baseline = self
+            .text
+            .next_baseline(context, style, text_area.size().width);
+        let result = self.text.render(context, text_area, style)?;
+
+        // The marker goes on the baseline of the first line of the item,
+        // it ends before the text and long markers reach into the indent to its left
+        if !self.marker_done && result.size.height > Mm::from(0) {
+            let font_cache = &context.font_cache;
+            let style = style.and(self.marker_style);
+            let right = self.marker_width - self.spacing;
+            match &self.marker {
+                Marker::Text(text) => {
+                    let x = right - style.str_width(font_cache, text);
+                    let top = baseline - glyph_height(font_cache, style);
+                    area.print_str(font_cache, Position::new(x, top), style, text)?;
+                }
+                Marker::Checkbox(checked) => {
+                    draw_checkbox(context, &area, style, right, baseline, *checked)
+                }
+            }
+            self.marker_done = true;
+        }
+        Ok(result)
+    }
+}
+
+/// Draw a checkbox ending at `right` that stands on the baseline like a glyph
+fn draw_checkbox(
+    context: &Context,
+    area: &Area<'_>,
+    style: Style,
+    right: Mm,
+    baseline: Mm,
+    checked: bool,
+) {
+    let size = glyph_height(&context.font_cache, style) * 0.6;
+    let x = right - size;
+    let y = baseline - size;
+    let point = |dx: f64, dy: f64| Position::new(x + size * dx, y + size * dy);
+    let line_style = Style::new().with_color(style.color().unwrap_or(Color::Rgb(0, 0, 0)));
+
+    area.draw_line(
+        vec![
+            point(0.0, 0.0),
+            point(1.0, 0.0),
+            point(1.0, 1.0),
+            point(0.0, 1.0),
+            point(0.0, 0.0),
+        ],
+        line_style,
+    );
+    if checked {
+        area.draw_line(
+            vec![point(0.2, 0.5), point(0.42, 0.75), point(0.8, 0.25)],
+            line_style,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbering_restarts_after_lines_that_are_not_list_items() {
+        let mut numbers = ListNumbers::default();
+        assert_eq!(numbers.next(&ListType::Ordered, 0), 1);
+        assert_eq!(numbers.next(&ListType::Ordered, 0), 2);
+        // A paragraph or an image between items, even one with text like "2. ", ends the list
+        numbers.end();
+        assert_eq!(numbers.next(&ListType::Ordered, 0), 1);
+    }
+
+    #[test]
+    fn numbering_restarts_when_the_list_type_changes() {
+        let mut numbers = ListNumbers::default();
+        assert_eq!(numbers.next(&ListType::Ordered, 0), 1);
+        assert_eq!(numbers.next(&ListType::Ordered, 0), 2);
+        assert_eq!(numbers.next(&ListType::Bullet, 0), 1);
+        assert_eq!(numbers.next(&ListType::Ordered, 0), 1);
+        assert_eq!(numbers.next(&ListType::Ordered, 0), 2);
+    }
+
+    #[test]
+    fn nested_levels_have_their_own_numbers() {
+        let mut numbers = ListNumbers::default();
+        assert_eq!(numbers.next(&ListType::Ordered, 0), 1);
+        assert_eq!(numbers.next(&ListType::Ordered, 1), 1);
+        assert_eq!(numbers.next(&ListType::Ordered, 1), 2);
+        assert_eq!(numbers.next(&ListType::Bullet, 2), 1);
+        // Going back up continues the outer list
+        assert_eq!(numbers.next(&ListType::Ordered, 0), 2);
+        // and restarts the lists nested in the previous item
+        assert_eq!(numbers.next(&ListType::Ordered, 1), 1);
+        // Skipping a level starts the deeper list on its own
+        assert_eq!(numbers.next(&ListType::Ordered, 3), 1);
+        assert_eq!(numbers.next(&ListType::Ordered, 1), 2);
+    }
+}
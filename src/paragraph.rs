@@ -0,0 +1,439 @@
+//! Paragraph element that wraps and draws styled runs itself.
+//!
+//! genpdf's `Paragraph` only knows about fonts and colours. Rendering the runs here
+//! makes it possible to draw decorations like underlines that depend on where each
+//! run ends up on the page.
+
+use crate::links::SharedLinks;
+use genpdf::{
+    error::Error,
+    fonts::FontCache,
+    render::Area,
+    style::{Color, Style},
+    Context, Element, Mm, Position, RenderResult, Rotation, Scale, Size,
+};
+use image::{DynamicImage, GrayImage, Luma, Rgb, RgbImage};
+
+/// Size of a typographic point in millimetres
+const PT: f64 = 0.352_778;
+
+/// Text sharing the same style and decorations
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Run {
+    pub text: String,
+    pub style: Style,
+    pub underline: bool,
+    pub strike: bool,
+    /// Colour of the box drawn behind the text
+    pub background: Option<Color>,
+    /// Target of the link, its area is recorded when the paragraph has links
+    pub link: Option<String>,
+}
+
+impl Run {
+    pub(crate) fn new(text: impl Into<String>, style: Style) -> Self {
+        Self {
+            text: text.into(),
+            style,
+            ..Default::default()
+        }
+    }
+}
+
+/// Horizontal placement of the lines of a paragraph
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Alignment {
+    Left,
+    Center,
+    Right,
+    /// Stretch the space between words so lines fill the width, except for the last line
+    Justify,
+}
+
+/// Part of a run placed on a wrapped line
+#[derive(Debug)]
+struct Piece {
+    /// Index of the run the text was taken from
+    run: usize,
+    text: String,
+}
+
+/// A paragraph made of runs that is wrapped to the width of the page
+pub(crate) struct RichParagraph {
+    runs: Vec<Run>,
+    alignment: Alignment,
+    /// The wrapped lines, computed on the first render
+    lines: Option<Vec<Vec<Piece>>>,
+    /// Index of the next line to render as paragraphs can span pages
+    next_line: usize,
+    /// Where the areas of links are recorded
+    links: Option<SharedLinks>,
+    /// Space between the page margin and the paragraph, needed to place links
+    left: Mm,
+}
+
+impl RichParagraph {
+    pub(crate) fn new(runs: Vec<Run>) -> Self {
+        Self {
+            runs,
+            alignment: Alignment::Left,
+            lines: None,
+            next_line: 0,
+            links: None,
+            left: Mm::from(0),
+        }
+    }
+
+    /// Record the areas of links in `links`, the paragraph is placed `left` of the page margin
+    pub(crate) fn with_links(mut self, links: Option<SharedLinks>, left: Mm) -> Self {
+        self.links = links;
+        self.left = left;
+        self
+    }
+
+    /// Measure the lines that are rendered next into an area of `width` without drawing them.
+    /// Returns the height of the lines that fit into `max_height` and whether lines are left over.
+    pub(crate) fn measure(
+        &mut self,
+        context: &Context,
+        style: Style,
+        width: Mm,
+        max_height: Mm,
+    ) -> (Mm, bool) {
+        if self.lines.is_none() {
+            self.lines = Some(self.wrap(context, style, width));
+        }
+        let lines = self.lines.as_deref().unwrap_or_default();
+
+        let mut height = Mm::from(0);
+        for line in &lines[self.next_line..] {
+            let metrics = line_metrics(&context.font_cache, &self.runs, style, line);
+            if height + metrics.line_height > max_height {
+                return (height, true);
+            }
+            height += metrics.line_height;
+        }
+        (height, false)
+    }
+
+    /// Distance from the top of the line that is rendered next to its baseline,
+    /// the runs are wrapped into `width` if they were not yet.
+    pub(crate) fn next_baseline(&mut self, context: &Context, style: Style, width: Mm) -> Mm {
+        if self.lines.is_none() {
+            self.lines = Some(self.wrap(context, style, width));
+        }
+        let lines = self.lines.as_deref().unwrap_or_default();
+        let line = lines.get(self.next_line).map_or(&[][..], Vec::as_slice);
+        line_metrics(&context.font_cache, &self.runs, style, line).glyph_height
+    }
+
+    /// Render the lines that fit into the top `max_height` of the area
+    pub(crate) fn render_within(
+        &mut self,
+        context: &Context,
+        area: Area<'_>,
+        style: Style,
+        max_height: Mm,
+    ) -> Result<RenderResult, Error> {
+        let font_cache = &context.font_cache;
+        let width = area.size().width;
+        if self.lines.is_none() {
+            self.lines = Some(self.wrap(context, style, width));
+        }
+        let Self {
+            runs,
+            alignment,
+            lines,
+            next_line,
+            links,
+            left,
+        } = self;
+        let lines = lines.as_deref().unwrap_or_default();
+
+        let mut result = RenderResult::default();
+        let mut y = Mm::from(0);
+        for (index, line) in lines.iter().enumerate().skip(*next_line) {
+            let metrics = line_metrics(font_cache, runs, style, line);
+            if y + metrics.line_height > max_height {
+                result.has_more = true;
+                break;
+            }
+
+            // Runs of different sizes share the baseline of the line
+            let baseline = y + metrics.glyph_height;
+            let line_width = line.iter().fold(Mm::from(0), |width, piece| {
+                width
+                    + style
+                        .and(runs[piece.run].style)
+                        .str_width(font_cache, &piece.text)
+            });
+            let free = width - line_width;
+            let last_line = index + 1 == lines.len();
+            let line_gaps = line.iter().map(|piece| gaps(&piece.text)).sum::<usize>();
+            let (mut x, gap) = match alignment {
+                Alignment::Left => (Mm::from(0), Mm::from(0)),
+                Alignment::Center => (free / 2.0, Mm::from(0)),
+                Alignment::Right => (free, Mm::from(0)),
+                Alignment::Justify if last_line || line_gaps == 0 => (Mm::from(0), Mm::from(0)),
+                Alignment::Justify => (Mm::from(0), free / line_gaps as f64),
+            };
+            for piece in line {
+                let run = &runs[piece.run];
+                let style = style.and(run.style);
+                let piece_width =
+                    style.str_width(font_cache, &piece.text) + gap * gaps(&piece.text) as f64;
+                if let Some(background) = run.background {
+                    // The box is drawn first so that it stays behind the text
+                    fill_rect(
+                        &area,
+                        Position::new(x, y),
+                        Size::new(piece_width, metrics.line_height),
+                        background,
+                    );
+                }
+
+                let top = baseline - glyph_height(font_cache, style);
+                if gap == Mm::from(0) {
+                    area.print_str(font_cache, Position::new(x, top), style, &piece.text)?;
+                } else {
+                    // Every word is placed on its own to stretch the space after it
+                    let mut word_x = x;
+                    for word in split_words(&piece.text) {
+                        area.print_str(font_cache, Position::new(word_x, top), style, word)?;
+                        word_x += style.str_width(font_cache, word) + gap * gaps(word) as f64;
+                    }
+                }
+                draw_decorations(&area, run, style, x, piece_width, baseline);
+                if let (Some(links), Some(url)) = (links.as_ref(), &run.link) {
+                    links.borrow_mut().add(
+                        &area,
+                        *left,
+                        Position::new(x, y),
+                        Size::new(piece_width, metrics.line_height),
+                        url,
+                    );
+                }
+                x += piece_width;
+            }
+
+            y += metrics.line_height;
+            *next_line += 1;
+        }
+
+        result.size = Size::new(width, y);
+        Ok(result)
+    }
+
+    pub(crate) fn aligned(mut self, alignment: Alignment) -> Self {
+        self.alignment = alignment;
+        self
+    }
+
+    /// Break the runs into lines that fit into `width`.
+    /// Lines are only broken after whitespace unless a single word is wider than a line.
+    /// A paragraph without text still has one empty line like in the editor.
+    fn wrap(&self, context: &Context, style: Style, width: Mm) -> Vec<Vec<Piece>> {
+        let font_cache = &context.font_cache;
+        let mut lines = Vec::new();
+        let mut line = Vec::new();
+        let mut line_width = Mm::from(0);
+
+        for (index, run) in self.runs.iter().enumerate() {
+            let style = style.and(run.style);
+            for word in split_words(&run.text) {
+                for part in split_long_word(word, |s| style.str_width(font_cache, s), width) {
+                    let part_width = style.str_width(font_cache, part.trim_end());
+                    if !line.is_empty() && line_width + part_width > width {
+                        finish_line(&mut lines, &mut line);
+                        line_width = Mm::from(0);
+                    }
+                    push_piece(&mut line, index, part);
+                    line_width += style.str_width(font_cache, part);
+                }
+            }
+        }
+        if !line.is_empty() || lines.is_empty() {
+            lines.push(line);
+        }
+        lines
+    }
+}
+
+impl Element for RichParagraph {
+    fn render(
+        &mut self,
+        context: &Context,
+        area: Area<'_>,
+        style: Style,
+    ) -> Result<RenderResult, Error> {
+        let height = area.size().height;
+        self.render_within(context, area, style, height)
+    }
+}
+
+/// Vertical space taken by the text of a line
+#[derive(Debug, Clone, Copy)]
+struct LineMetrics {
+    /// Distance from the top of the line to the baseline
+    glyph_height: Mm,
+    line_height: Mm,
+}
+
+impl LineMetrics {
+    fn new(font_cache: &FontCache, style: Style) -> Self {
+        Self {
+            glyph_height: glyph_height(font_cache, style),
+            line_height: style.line_height(font_cache),
+        }
+    }
+
+    /// Metrics of a line that fits both, with the space below the baseline of the deeper one
+    fn max(self, other: Self) -> Self {
+        let glyph_height = self.glyph_height.max(other.glyph_height);
+        let below =
+            (self.line_height - self.glyph_height).max(other.line_height - other.glyph_height);
+        Self {
+            glyph_height,
+            line_height: glyph_height + below,
+        }
+    }
+}
+
+/// Metrics of a wrapped line, the tallest run sets the baseline
+fn line_metrics(font_cache: &FontCache, runs: &[Run], style: Style, line: &[Piece]) -> LineMetrics {
+    line.iter()
+        .map(|piece| LineMetrics::new(font_cache, style.and(runs[piece.run].style)))
+        .reduce(LineMetrics::max)
+        .unwrap_or_else(|| LineMetrics::new(font_cache, style))
+}
+
+/// Height of the glyphs of a style, genpdf prints text this far above the baseline
+pub(crate) fn glyph_height(font_cache: &FontCache, style: Style) -> Mm {
+    style.font(font_cache).glyph_height(style.font_size())
+}
+
+/// Split text after each run of whitespace, keeping the whitespace with the word before it
+fn split_words(text: &str) -> Vec<&str> {
+    let mut words = Vec::new();
+    let mut start = 0;
+    let mut in_space = false;
+    for (index, c) in text.char_indices() {
+        if c.is_whitespace() {
+            in_space = true;
+        } else if in_space {
+            words.push(&text[start..index]);
+            start = index;
+            in_space = false;
+        }
+    }
+    if start < text.len() {
+        words.push(&text[start..]);
+    }
+    words
+}
+
+/// Number of stretchable spaces in text, the whitespace after each of its words
+fn gaps(text: &str) -> usize {
+    split_words(text)
+        .iter()
+        .filter(|word| word.ends_with(char::is_whitespace))
+        .count()
+}
+
+/// Split a word that is wider than a line into parts that fit
+fn split_long_word(word: &str, str_width: impl Fn(&str) -> Mm, width: Mm) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut rest = word;
+    while str_width(rest.trim_end()) > width {
+        let end = rest
+            .char_indices()
+            .map(|(index, c)| index + c.len_utf8())
+            .take_while(|end| str_width(&rest[..*end]) <= width)
+            .last()
+            // Take at least one character so wrapping always makes progress
+            .unwrap_or_else(|| rest.chars().next().map_or(rest.len(), char::len_utf8));
+        parts.push(&rest[..end]);
+        rest = &rest[end..];
+    }
+    if !rest.is_empty() {
+        parts.push(rest);
+    }
+    parts
+}
+
+/// Add text to a line, merging it into the last piece if it is from the same run
+fn push_piece(line: &mut Vec<Piece>, run: usize, text: &str) {
+    match line.last_mut() {
+        Some(last) if last.run == run => last.text.push_str(text),
+        _ => line.push(Piece {
+            run,
+            text: text.to_string(),
+        }),
+    }
+}
+
+/// End a line that is broken by wrapping, whitespace at the break is not drawn
+fn finish_line(lines: &mut Vec<Vec<Piece>>, line: &mut Vec<Piece>) {
+    if let Some(last) = line.last_mut() {
+        last.text.truncate(last.text.trim_end().len());
+    }
+    lines.push(std::mem::take(line));
+}
+
+/// Font size of a style in millimetres
+fn em(style: Style) -> Mm {
+    Mm::from(f64::from(style.font_size()) * PT)
+}
+
+/// Length in typographic points, the unit of PDF coordinates
+pub(crate) fn points(length: Mm) -> f64 {
+    f64::from(length) / PT
+}
+
+/// Fill a rectangle by stretching an image of a single pixel over it,
+/// genpdf can only stroke lines and not fill shapes.
+pub(crate) fn fill_rect(area: &Area<'_>, position: Position, size: Size, color: Color) {
+    let pixel = match color {
+        Color::Rgb(r, g, b) => DynamicImage::ImageRgb8(RgbImage::from_pixel(1, 1, Rgb([r, g, b]))),
+        Color::Cmyk(c, m, y, k) => {
+            let channel = |value: u8| ((255 - u16::from(value)) * (255 - u16::from(k)) / 255) as u8;
+            DynamicImage::ImageRgb8(RgbImage::from_pixel(
+                1,
+                1,
+                Rgb([channel(c), channel(m), channel(y)]),
+            ))
+        }
+        Color::Greyscale(value) => {
+            DynamicImage::ImageLuma8(GrayImage::from_pixel(1, 1, Luma([value])))
+        }
+    };
+    // At 72 dpi the pixel is one point wide, so the scale is the size in points
+    area.add_image(
+        &pixel,
+        // Images are placed by their bottom left corner
+        Position::new(position.x, position.y + size.height),
+        Scale::new(points(size.width), points(size.height)),
+        Rotation::default(),
+        Some(72.0),
+    );
+}
+
+/// Draw the underline and strikethrough of a piece of a run
+fn draw_decorations(area: &Area<'_>, run: &Run, style: Style, x: Mm, width: Mm, baseline: Mm) {
+    let em = em(style);
+    // genpdf strokes lines with the default width, so only the colour follows the text
+    let line_style = Style::new().with_color(style.color().unwrap_or(Color::Rgb(0, 0, 0)));
+    let draw = |y: Mm| {
+        area.draw_line(
+            vec![Position::new(x, y), Position::new(x + width, y)],
+            line_style,
+        )
+    };
+
+    if run.underline {
+        draw(baseline + em * 0.1);
+    }
+    if run.strike {
+        draw(baseline - em * 0.3);
+    }
+}
@@ -0,0 +1,59 @@
+//! Element for Quill blockquotes.
+
+use genpdf::{
+    elements::LinearLayout, error::Error, render::Area, style::Style, Context, Element, Margins,
+    Mm, Position, RenderResult, Size,
+};
+
+use crate::{paragraph::fill_rect, theme::QuoteStyle};
+
+/// Consecutive quote lines rendered as one indented block with a rule on the left
+pub(crate) struct Quote {
+    content: LinearLayout,
+    style: QuoteStyle,
+}
+
+impl Quote {
+    pub(crate) fn new(content: LinearLayout, style: QuoteStyle) -> Self {
+        Self { content, style }
+    }
+
+    /// Space between the left edge of a quote and its content
+    pub(crate) fn inset(style: QuoteStyle) -> Mm {
+        style.rule_thickness + style.indent
+    }
+}
+
+impl Element for Quote {
+    fn render(
+        &mut self,
+        context: &Context,
+        area: Area<'_>,
+        style: Style,
+    ) -> Result<RenderResult, Error> {
+        let quote = self.style;
+        let mut style = style;
+        if quote.italic {
+            style.set_italic();
+        }
+        if let Some(color) = quote.color {
+            style.set_color(color);
+        }
+
+        let mut content_area = area.clone();
+        content_area.add_margins(Margins::trbl(0, 0, 0, Self::inset(quote)));
+        let mut result = self.content.render(context, content_area, style)?;
+
+        // The rule spans the part of the quote on this page
+        if result.size.height > Mm::from(0) {
+            fill_rect(
+                &area,
+                Position::new(0, 0),
+                Size::new(quote.rule_thickness, result.size.height),
+                quote.rule_color,
+            );
+        }
+        result.size.width = area.size().width;
+        Ok(result)
+    }
+}
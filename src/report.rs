@@ -0,0 +1,65 @@
+//! Report of the content that could not be rendered.
+//!
+//! `DeltaPdf::write_to_pdf()` tolerates content it can not render and lists it in a
+//! [`RenderReport`] instead, so callers can check what a PDF is missing.
+
+use std::fmt;
+
+use crate::delta::Attribute;
+
+/// Content of a Delta that was left out of the PDF
+#[derive(Debug, Clone, PartialEq)]
+pub enum Dropped {
+    /// A retain or delete, only inserts are rendered
+    Change,
+    /// An embed that is not rendered, named by its type
+    Embed(String),
+    /// An attribute that is not rendered
+    Attribute(Attribute),
+}
+
+/// A single piece of content that was left out of the PDF
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderWarning {
+    /// Index of the op in the Delta
+    pub op: usize,
+    pub dropped: Dropped,
+}
+
+impl fmt::Display for RenderWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.dropped {
+            Dropped::Change => write!(f, "op {}: retain or delete ignored", self.op),
+            Dropped::Embed(embed) => write!(f, "op {}: embed `{}` skipped", self.op, embed),
+            Dropped::Attribute(attribute) => {
+                write!(
+                    f,
+                    "op {}: attribute `{}` ignored",
+                    self.op,
+                    attribute.name()
+                )
+            }
+        }
+    }
+}
+
+/// Everything that was left out while rendering a Delta
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RenderReport {
+    pub warnings: Vec<RenderWarning>,
+}
+
+impl RenderReport {
+    /// Whether the whole Delta was rendered
+    pub fn is_empty(&self) -> bool {
+        self.warnings.is_empty()
+    }
+
+    pub(crate) fn push(&mut self, op: usize, dropped: Dropped) {
+        self.warnings.push(RenderWarning { op, dropped });
+    }
+
+    pub(crate) fn drop_attribute(&mut self, op: usize, attribute: &Attribute) {
+        self.push(op, Dropped::Attribute(attribute.clone()));
+    }
+}
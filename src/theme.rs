@@ -0,0 +1,365 @@
+//! Visual settings used when rendering a Delta to PDF.
+//!
+//! A [`Theme`] can be set on a `DeltaPdf` to change the look of the produced document
+//! without touching the Delta itself.
+//!
+//! ```
+//! use quill_delta_pdf::theme::Theme;
+//!
+//! let mut theme = Theme::default();
+//! theme.list.bullets = vec!["–".into()];
+//! theme.paragraph_spacing = quill_delta_pdf::theme::Spacing::trbl(0, 0, 2, 0);
+//! ```
+
+use genpdf::{style::Color, Margins, Mm};
+
+/// All visual settings used by `DeltaPdf`
+#[derive(Debug, Clone)]
+pub struct Theme {
+    /// Space between the edges of the page and the content when rendering with
+    /// `DeltaPdf::render()`, which replaces the page decorator of the document
+    pub page_margins: Spacing,
+    /// Space around regular paragraphs
+    pub paragraph_spacing: Spacing,
+    /// Space around images
+    pub image_spacing: Spacing,
+    /// Styles of the header levels 1 to 6
+    pub headings: [HeadingStyle; 6],
+    pub list: ListStyle,
+    pub quote: QuoteStyle,
+    pub code: CodeStyle,
+    pub link: LinkStyle,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            page_margins: Spacing::all(10),
+            paragraph_spacing: Spacing::trbl(0, 0, 1, 0),
+            image_spacing: Spacing::all(1),
+            headings: [
+                HeadingStyle::new(18).with_spacing(Spacing::trbl(2, 0, 1, 0)),
+                HeadingStyle::new(16).with_spacing(Spacing::trbl(2, 0, 1, 0)),
+                HeadingStyle::new(14),
+                HeadingStyle::new(13),
+                HeadingStyle::new(12),
+                HeadingStyle::new(11),
+            ],
+            list: ListStyle::default(),
+            quote: QuoteStyle::default(),
+            code: CodeStyle::default(),
+            link: LinkStyle::default(),
+        }
+    }
+}
+
+impl Theme {
+    /// Get the style of a header level between 1 and 6
+    pub fn heading(&self, level: u8) -> Option<&HeadingStyle> {
+        self.headings.get(usize::from(level).checked_sub(1)?)
+    }
+
+    /// Get the mutable style of a header level between 1 and 6
+    pub fn heading_mut(&mut self, level: u8) -> Option<&mut HeadingStyle> {
+        self.headings.get_mut(usize::from(level).checked_sub(1)?)
+    }
+}
+
+/// Space around the sides of an element.
+/// Unlike genpdf's `Margins` the sides can be read, which is needed to place links on the page.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Spacing {
+    pub top: Mm,
+    pub right: Mm,
+    pub bottom: Mm,
+    pub left: Mm,
+}
+
+impl Spacing {
+    /// Create spacing from the top, right, bottom and left sides
+    pub fn trbl(
+        top: impl Into<Mm>,
+        right: impl Into<Mm>,
+        bottom: impl Into<Mm>,
+        left: impl Into<Mm>,
+    ) -> Self {
+        Self {
+            top: top.into(),
+            right: right.into(),
+            bottom: bottom.into(),
+            left: left.into(),
+        }
+    }
+
+    /// Create spacing from the vertical and horizontal sides
+    pub fn vh(vertical: impl Into<Mm>, horizontal: impl Into<Mm>) -> Self {
+        let (vertical, horizontal) = (vertical.into(), horizontal.into());
+        Self::trbl(vertical, horizontal, vertical, horizontal)
+    }
+
+    /// Create the same spacing on all sides
+    pub fn all(spacing: impl Into<Mm>) -> Self {
+        let spacing = spacing.into();
+        Self::trbl(spacing, spacing, spacing, spacing)
+    }
+}
+
+impl From<Spacing> for Margins {
+    fn from(spacing: Spacing) -> Self {
+        Margins::trbl(spacing.top, spacing.right, spacing.bottom, spacing.left)
+    }
+}
+
+impl<T: Into<Mm>, R: Into<Mm>, B: Into<Mm>, L: Into<Mm>> From<(T, R, B, L)> for Spacing {
+    fn from((top, right, bottom, left): (T, R, B, L)) -> Self {
+        Self::trbl(top, right, bottom, left)
+    }
+}
+
+impl<V: Into<Mm>, H: Into<Mm>> From<(V, H)> for Spacing {
+    fn from((vertical, horizontal): (V, H)) -> Self {
+        Self::vh(vertical, horizontal)
+    }
+}
+
+impl From<Mm> for Spacing {
+    fn from(spacing: Mm) -> Self {
+        Self::all(spacing)
+    }
+}
+
+/// Style used to render one level of Quill headers
+#[derive(Debug, Clone, Copy)]
+pub struct HeadingStyle {
+    pub font_size: u8,
+    pub bold: bool,
+    /// Space around the heading
+    pub spacing: Spacing,
+}
+
+impl HeadingStyle {
+    /// Create a bold heading style with the given font size
+    pub fn new(font_size: u8) -> Self {
+        Self {
+            font_size,
+            bold: true,
+            spacing: Spacing::trbl(1, 0, 1, 0),
+        }
+    }
+
+    /// Set the space around the heading
+    pub fn with_spacing(mut self, spacing: impl Into<Spacing>) -> Self {
+        self.spacing = spacing.into();
+        self
+    }
+
+    /// Set whether the heading is bold
+    pub fn with_bold(mut self, bold: bool) -> Self {
+        self.bold = bold;
+        self
+    }
+}
+
+/// Style of bullet and ordered lists
+#[derive(Debug, Clone)]
+pub struct ListStyle {
+    /// Indentation added by every nesting level, top level items are indented once.
+    /// The markers of the items are placed in the last indentation before their text.
+    pub indent: Mm,
+    /// Space between list markers and the text of the items
+    pub marker_spacing: Mm,
+    /// Markers of bullet list items by nesting level, repeated for deeper levels
+    pub bullets: Vec<String>,
+    /// Numbering of ordered list items by nesting level, repeated for deeper levels
+    pub numbering: Vec<Numbering>,
+    /// Text placed after the number of ordered list items
+    pub number_suffix: String,
+    /// Whether the text of checked checklist items is struck through
+    pub strike_checked: bool,
+}
+
+impl Default for ListStyle {
+    fn default() -> Self {
+        Self {
+            indent: Mm::from(6),
+            marker_spacing: Mm::from(2),
+            bullets: vec!["•".into(), "◦".into(), "▪".into()],
+            numbering: vec![
+                Numbering::Decimal,
+                Numbering::LowerAlpha,
+                Numbering::LowerRoman,
+            ],
+            number_suffix: ".".into(),
+            strike_checked: false,
+        }
+    }
+}
+
+impl ListStyle {
+    /// Get the marker of bullet list items at a nesting level
+    pub fn bullet(&self, level: u8) -> &str {
+        match self.bullets.len() {
+            0 => "",
+            len => &self.bullets[usize::from(level) % len],
+        }
+    }
+
+    /// Get the marker of an ordered list item at a nesting level
+    pub fn number(&self, level: u8, number: u32) -> String {
+        let numbering = match self.numbering.len() {
+            0 => Numbering::Decimal,
+            len => self.numbering[usize::from(level) % len],
+        };
+        format!("{}{}", numbering.format(number), self.number_suffix)
+    }
+}
+
+/// How the items of ordered lists are counted
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Numbering {
+    /// 1, 2, 3
+    Decimal,
+    /// a, b, c, continued with aa, ab after z
+    LowerAlpha,
+    /// i, ii, iii
+    LowerRoman,
+}
+
+impl Numbering {
+    /// Format the number of a list item, starting at 1
+    pub fn format(self, number: u32) -> String {
+        match self {
+            Numbering::Decimal => number.to_string(),
+            Numbering::LowerAlpha => {
+                let mut letters = Vec::new();
+                let mut rest = number;
+                while rest > 0 {
+                    rest -= 1;
+                    letters.push(char::from(b'a' + (rest % 26) as u8));
+                    rest /= 26;
+                }
+                letters.iter().rev().collect()
+            }
+            Numbering::LowerRoman => {
+                const NUMERALS: [(u32, &str); 13] = [
+                    (1000, "m"),
+                    (900, "cm"),
+                    (500, "d"),
+                    (400, "cd"),
+                    (100, "c"),
+                    (90, "xc"),
+                    (50, "l"),
+                    (40, "xl"),
+                    (10, "x"),
+                    (9, "ix"),
+                    (5, "v"),
+                    (4, "iv"),
+                    (1, "i"),
+                ];
+                let mut roman = String::new();
+                let mut rest = number;
+                for (value, numeral) in NUMERALS {
+                    while rest >= value {
+                        roman.push_str(numeral);
+                        rest -= value;
+                    }
+                }
+                roman
+            }
+        }
+    }
+}
+
+/// Style of blockquotes
+#[derive(Debug, Clone, Copy)]
+pub struct QuoteStyle {
+    /// Space between the left rule and the quoted text
+    pub indent: Mm,
+    pub italic: bool,
+    /// Colour of the quoted text
+    pub color: Option<Color>,
+    pub rule_color: Color,
+    pub rule_thickness: Mm,
+}
+
+impl Default for QuoteStyle {
+    fn default() -> Self {
+        Self {
+            indent: Mm::from(5),
+            italic: false,
+            color: Some(Color::Greyscale(80)),
+            rule_color: Color::Greyscale(204),
+            rule_thickness: Mm::from(1),
+        }
+    }
+}
+
+/// Style of code
+#[derive(Debug, Clone, Copy)]
+pub struct CodeStyle {
+    /// Font size of code, the document font size is used if not set
+    pub font_size: Option<u8>,
+    /// Colour of the code text
+    pub color: Option<Color>,
+    /// Colour of the box drawn behind code
+    pub background: Option<Color>,
+    /// Space between the background box and code blocks
+    pub padding: Spacing,
+    /// Colours of highlighted code blocks, highlighting is disabled if not set
+    pub syntax: Option<SyntaxColors>,
+}
+
+impl Default for CodeStyle {
+    fn default() -> Self {
+        Self {
+            font_size: Some(10),
+            color: None,
+            background: Some(Color::Rgb(240, 240, 240)),
+            padding: Spacing::all(2),
+            syntax: Some(SyntaxColors::default()),
+        }
+    }
+}
+
+/// Colours of the tokens in highlighted code blocks
+#[derive(Debug, Clone, Copy)]
+pub struct SyntaxColors {
+    pub keyword: Color,
+    pub string: Color,
+    pub number: Color,
+    pub comment: Color,
+    /// Added lines of diffs
+    pub addition: Color,
+    /// Removed lines of diffs
+    pub deletion: Color,
+}
+
+impl Default for SyntaxColors {
+    fn default() -> Self {
+        Self {
+            keyword: Color::Rgb(215, 58, 73),
+            string: Color::Rgb(3, 47, 98),
+            number: Color::Rgb(0, 92, 197),
+            comment: Color::Rgb(106, 115, 125),
+            addition: Color::Rgb(34, 134, 58),
+            deletion: Color::Rgb(179, 29, 40),
+        }
+    }
+}
+
+/// Style of links
+#[derive(Debug, Clone, Copy)]
+pub struct LinkStyle {
+    /// Colour of linked text without a colour of its own
+    pub color: Option<Color>,
+    pub underline: bool,
+}
+
+impl Default for LinkStyle {
+    fn default() -> Self {
+        Self {
+            color: Some(Color::Rgb(0, 102, 204)),
+            underline: true,
+        }
+    }
+}